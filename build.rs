@@ -0,0 +1,26 @@
+// Generates the C header for the "capi" feature as $OUT_DIR/k_log.h, nothing to do otherwise.
+// include/k_log.h is a copy of it kept for C users, refresh it by hand when the API changes.
+
+fn main() {
+    #[cfg(feature = "capi")]
+    {
+        let dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        let out = std::env::var("OUT_DIR").unwrap();
+        println!("cargo:rerun-if-changed=src/capi.rs");
+        let config = cbindgen::Config {
+            language: cbindgen::Language::C,
+            include_guard: Some("K_LOG_H".into()),
+            header: Some("/* Generated by cbindgen from src/capi.rs, do not edit */".into()),
+            after_includes: Some("\ntypedef struct Frac Frac;\ntypedef struct KFrac KFrac;".into()),
+            cpp_compat: true,
+            ..Default::default()
+        };
+        match cbindgen::Builder::new()
+            .with_config( config )
+            .with_src( format!("{}/src/capi.rs", dir) )
+            .generate() {
+            Ok(b) => { b.write_to_file( format!("{}/k_log.h", out) ); },
+            Err(e) => println!("cargo:warning=k_log.h not generated: {}", e),
+        }
+    }
+}
@@ -0,0 +1,199 @@
+/* Generated by cbindgen from src/capi.rs, do not edit */
+
+#ifndef K_LOG_H
+#define K_LOG_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+typedef struct Frac Frac;
+typedef struct KFrac KFrac;
+
+#define KL_OK 0
+
+#define KL_DONE 1
+
+#define KL_ERR_BELOW_ONE -1
+
+#define KL_ERR_INFINITE -2
+
+#define KL_ERR_CONVERGENTS -3
+
+#define KL_ERR_TIME -4
+
+#define KL_ERR_BITS -5
+
+#define KL_ERR_STALL -6
+
+#define KL_ERR_CANCELLED -7
+
+#define KL_ERR_NULL -8
+
+#define KL_ERR_PANIC -9
+
+#define KL_ERR_IO -10
+
+#define KL_ERR_ARG -11
+
+typedef struct KLogHandle KLogHandle;
+
+typedef struct RegFracHandle RegFracHandle;
+
+typedef struct KlBudget {
+  int64_t convergents;
+  uint64_t millis;
+  uint64_t bits;
+  int64_t stall;
+} KlBudget;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * # Safety
+ * 's' must be null or a string from this library, not freed before.
+ */
+void kl_string_free(char *s);
+
+Frac *kl_frac_new(int64_t num, int64_t den);
+
+/**
+ * # Safety
+ * 's' must be null or a valid NUL-terminated string.
+ */
+Frac *kl_frac_parse(const char *s);
+
+/**
+ * # Safety
+ * 'f' must be null or a live Frac handle.
+ */
+char *kl_frac_to_string(const Frac *f);
+
+/**
+ * # Safety
+ * 'f' must be null or a live Frac handle, which is invalid afterwards.
+ */
+void kl_frac_free(Frac *f);
+
+/**
+ * # Safety
+ * 'name' must be null or a valid NUL-terminated string.
+ */
+KFrac *kl_kfrac_constant(const char *name);
+
+/**
+ * # Safety
+ * 'k' must be null or a live KFrac handle.
+ */
+int32_t kl_kfrac_prop(KFrac *k, int64_t count);
+
+/**
+ * # Safety
+ * 'k' must be null or a live KFrac handle.
+ */
+int64_t kl_kfrac_n(const KFrac *k);
+
+/**
+ * # Safety
+ * 'k' must be null or a live KFrac handle, 'b' null or a valid budget, 'radius' null or writable.
+ */
+char *kl_kfrac_evaluate(KFrac *k, uint32_t bits, const KlBudget *b, double *radius);
+
+/**
+ * # Safety
+ * 'k' must be null or a live KFrac handle, which is invalid afterwards.
+ */
+void kl_kfrac_free(KFrac *k);
+
+/**
+ * # Safety
+ * 'f' must be null or a live Frac handle, which is copied.
+ */
+struct KLogHandle *kl_klog_from_frac(const Frac *f);
+
+/**
+ * # Safety
+ * 'k' must be null or a live KFrac handle, which is taken over (and invalid afterwards).
+ */
+struct KLogHandle *kl_klog_from_kfrac(KFrac *k);
+
+/**
+ * # Safety
+ * 'h' must be null or a live KLog handle, 'b' null or a valid budget.
+ */
+int32_t kl_klog_red(struct KLogHandle *h, uintptr_t digits, const struct KlBudget *b);
+
+/**
+ * # Safety
+ * 'h' must be null or a live KLog handle.
+ */
+uintptr_t kl_klog_len(const struct KLogHandle *h);
+
+/**
+ * # Safety
+ * 'h' must be null or a live KLog handle, 'out' null or writable for 'cap' bytes.
+ */
+uintptr_t kl_klog_tape(const struct KLogHandle *h, uint8_t *out, uintptr_t cap);
+
+/**
+ * # Safety
+ * 'h' must be null or a live KLog handle.
+ */
+bool kl_klog_done(const struct KLogHandle *h);
+
+/**
+ * # Safety
+ * 'h' must be null or a live KLog handle.
+ */
+char *kl_klog_to_string(const struct KLogHandle *h);
+
+/**
+ * # Safety
+ * 'h' must be null or a live KLog handle, which is invalid afterwards.
+ */
+void kl_klog_free(struct KLogHandle *h);
+
+/**
+ * # Safety
+ * 'f' must be null or a live Frac handle, which is copied.
+ */
+struct RegFracHandle *kl_regfrac_from_frac(const Frac *f);
+
+/**
+ * # Safety
+ * 'k' must be null or a live KFrac handle, which is taken over (and invalid afterwards).
+ */
+struct RegFracHandle *kl_regfrac_from_kfrac(KFrac *k);
+
+/**
+ * # Safety
+ * 'h' must be null or a live RegFrac handle, 'b' null or a valid budget.
+ */
+int32_t kl_regfrac_red(struct RegFracHandle *h, uintptr_t terms, const struct KlBudget *b);
+
+/**
+ * # Safety
+ * 'h' must be null or a live RegFrac handle.
+ */
+uintptr_t kl_regfrac_len(const struct RegFracHandle *h);
+
+/**
+ * # Safety
+ * 'h' must be null or a live RegFrac handle, 'out' null or writable for 'cap' terms.
+ */
+uintptr_t kl_regfrac_terms(const struct RegFracHandle *h, uint64_t *out, uintptr_t cap);
+
+/**
+ * # Safety
+ * 'h' must be null or a live RegFrac handle, which is invalid afterwards.
+ */
+void kl_regfrac_free(struct RegFracHandle *h);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* K_LOG_H */
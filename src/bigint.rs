@@ -0,0 +1,367 @@
+// The pure-Rust Integer (feature "bigint", without "gmp"): a num-bigint BigInt dressed up with the
+// parts of rug's Integer API the library uses, so that the rest of the code reads the same for both
+// backends. Operators and comparisons take Integer, &Integer and the primitive integers; on
+// references only, operators give an Incomplete, as rug does.
+
+use num_bigint::BigInt;
+use num_integer::Integer as _;
+use num_traits::{Signed, ToPrimitive};
+use std::cmp::Ordering;
+use std::fmt::{Debug, Display};
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Rem, RemAssign, Shl, ShlAssign,
+               Shr, ShrAssign, Sub, SubAssign};
+use std::str::FromStr;
+use crate::int::IntOps;
+
+#[derive(Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Integer( BigInt );
+
+impl Integer {
+    pub fn new() -> Self {
+        Integer::default()
+    }
+    pub fn find_one( &self, start: u32 ) -> Option<u32> {
+        // only used from bit 0 on, i.e. the trailing zeros
+        debug_assert!( start == 0 );
+        self.0.trailing_zeros().map( |z| z as u32 )
+    }
+    pub fn significant_bits( &self ) -> u32 {
+        self.0.bits() as u32
+    }
+    pub fn is_even( &self ) -> bool {
+        self.0.is_even()
+    }
+    pub fn is_odd( &self ) -> bool {
+        self.0.is_odd()
+    }
+    pub fn cmp0( &self ) -> Ordering {
+        self.0.sign().cmp( &num_bigint::Sign::NoSign )
+    }
+    pub fn cmp_abs( &self, other: &Integer ) -> Ordering {
+        self.0.magnitude().cmp( other.0.magnitude() )
+    }
+    pub fn abs( self ) -> Self {
+        Integer( self.0.abs() )
+    }
+    pub fn to_string_radix( &self, radix: i32 ) -> String {
+        self.0.to_str_radix( radix as u32 )
+    }
+    pub fn from_str_radix( s: &str, radix: i32 ) -> Result<Self, ParseIntegerError> {
+        num_traits::Num::from_str_radix( s, radix as u32 ).map( Integer ).map_err( |_| ParseIntegerError )
+    }
+    pub fn to_u128( &self ) -> Option<u128> {
+        self.0.to_u128()
+    }
+    pub fn to_i128( &self ) -> Option<i128> {
+        self.0.to_i128()
+    }
+    pub fn to_u64( &self ) -> Option<u64> {
+        self.0.to_u64()
+    }
+    pub fn to_i64( &self ) -> Option<i64> {
+        self.0.to_i64()
+    }
+    pub fn to_f64( &self ) -> f64 {
+        self.0.to_f64().unwrap_or( f64::NAN )
+    }
+}
+
+pub trait Pow<E> {
+    // as rug::ops::Pow
+    type Output;
+    fn pow( self, e: E ) -> Self::Output;
+}
+
+impl Pow<u32> for Integer {
+    type Output = Integer;
+    fn pow( self, e: u32 ) -> Integer {
+        Integer( num_traits::Pow::pow( self.0, e ) )
+    }
+}
+
+impl IntOps for Integer {
+    fn gcd_of( &self, other: &Self ) -> Self {
+        Integer( self.0.gcd( &other.0 ) )
+    }
+    fn div_rem_of( &self, d: &Self ) -> (Self, Self) {
+        let (q, r) = self.0.div_rem( &d.0 );
+        (Integer(q), Integer(r))
+    }
+    fn div_rem_floor_of( &self, d: &Self ) -> (Self, Self) {
+        let (q, r) = self.0.div_mod_floor( &d.0 );
+        (Integer(q), Integer(r))
+    }
+    fn div_rem_ceil_of( &self, d: &Self ) -> (Self, Self) {
+        let q = self.0.div_ceil( &d.0 );
+        let r = &self.0 - &q * &d.0;
+        (Integer(q), Integer(r))
+    }
+    fn div_rem_round_of( &self, d: &Self ) -> (Self, Self) {
+        // truncate, then step away from zero when the remainder is at least half of d
+        let (mut q, mut r) = self.0.div_rem( &d.0 );
+        if (r.magnitude() << 1u32) >= *d.0.magnitude() {
+            if (r.sign() == num_bigint::Sign::Minus) == (d.0.sign() == num_bigint::Sign::Minus) {
+                q += 1;
+                r -= &d.0;
+            } else {
+                q -= 1;
+                r += &d.0;
+            }
+        }
+        (Integer(q), Integer(r))
+    }
+    fn abs_of( &self ) -> Self {
+        Integer( self.0.abs() )
+    }
+    fn pow_of( &self, e: u32 ) -> Self {
+        Integer( self.0.pow( e ) )
+    }
+    fn sqrt_of( &self ) -> Self {
+        Integer( self.0.sqrt() )
+    }
+    fn root_of( &self, n: u32 ) -> Self {
+        Integer( self.0.nth_root( n ) )
+    }
+    fn magnitude_be( &self ) -> Vec<u8> {
+        if self.0.sign() == num_bigint::Sign::NoSign { Vec::new() } else { self.0.magnitude().to_bytes_be() }
+    }
+    fn from_magnitude_be( b: &[u8] ) -> Self {
+        Integer( BigInt::from_bytes_be( num_bigint::Sign::Plus, b ) )
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseIntegerError;
+
+impl Display for ParseIntegerError {
+    fn fmt( &self, f: &mut std::fmt::Formatter ) -> std::fmt::Result {
+        write!( f, "invalid integer" )
+    }
+}
+
+impl std::error::Error for ParseIntegerError {}
+
+impl FromStr for Integer {
+    type Err = ParseIntegerError;
+    fn from_str( s: &str ) -> Result<Self, ParseIntegerError> {
+        s.parse::<BigInt>().map( Integer ).map_err( |_| ParseIntegerError )
+    }
+}
+
+impl Display for Integer {
+    fn fmt( &self, f: &mut std::fmt::Formatter ) -> std::fmt::Result {
+        Display::fmt( &self.0, f )
+    }
+}
+
+impl Debug for Integer {
+    fn fmt( &self, f: &mut std::fmt::Formatter ) -> std::fmt::Result {
+        Display::fmt( &self.0, f )
+    }
+}
+
+pub struct Incomplete<'a> {
+    // What operators on references give, like rug's incomplete computations: taken into an Integer
+    // by Integer::from or an assigning operator, so that the same calls are needed for both backends
+    value: BigInt,
+    _refs: std::marker::PhantomData<&'a Integer>,
+}
+
+impl Incomplete<'_> {
+    fn new( value: BigInt ) -> Self {
+        Incomplete { value, _refs: std::marker::PhantomData }
+    }
+}
+
+impl From<Incomplete<'_>> for Integer {
+    fn from( a: Incomplete<'_> ) -> Self {
+        Integer( a.value )
+    }
+}
+
+impl From<&Integer> for Integer {
+    fn from( a: &Integer ) -> Self {
+        a.clone()
+    }
+}
+
+impl Neg for Integer {
+    type Output = Integer;
+    fn neg( self ) -> Integer {
+        Integer( -self.0 )
+    }
+}
+
+impl<'a> Neg for &'a Integer {
+    type Output = Incomplete<'a>;
+    fn neg( self ) -> Incomplete<'a> {
+        Incomplete::new( -&self.0 )
+    }
+}
+
+macro_rules! int_op {
+    ($Op:ident, $op:ident, $OpAssign:ident, $op_assign:ident) => {
+        impl $Op<Integer> for Integer {
+            type Output = Integer;
+            fn $op( self, other: Integer ) -> Integer {
+                Integer( self.0.$op( other.0 ) )
+            }
+        }
+        impl $Op<&Integer> for Integer {
+            type Output = Integer;
+            fn $op( self, other: &Integer ) -> Integer {
+                Integer( self.0.$op( &other.0 ) )
+            }
+        }
+        impl $Op<Integer> for &Integer {
+            type Output = Integer;
+            fn $op( self, other: Integer ) -> Integer {
+                Integer( (&self.0).$op( other.0 ) )
+            }
+        }
+        impl<'a> $Op<&'a Integer> for &'a Integer {
+            type Output = Incomplete<'a>;
+            fn $op( self, other: &'a Integer ) -> Incomplete<'a> {
+                Incomplete::new( (&self.0).$op( &other.0 ) )
+            }
+        }
+        impl $Op<Incomplete<'_>> for Integer {
+            type Output = Integer;
+            fn $op( self, other: Incomplete<'_> ) -> Integer {
+                Integer( self.0.$op( other.value ) )
+            }
+        }
+        impl $OpAssign<Incomplete<'_>> for Integer {
+            fn $op_assign( &mut self, other: Incomplete<'_> ) {
+                self.0.$op_assign( other.value );
+            }
+        }
+        impl $OpAssign<Integer> for Integer {
+            fn $op_assign( &mut self, other: Integer ) {
+                self.0.$op_assign( other.0 );
+            }
+        }
+        impl $OpAssign<&Integer> for Integer {
+            fn $op_assign( &mut self, other: &Integer ) {
+                self.0.$op_assign( &other.0 );
+            }
+        }
+        int_op!( @prim $Op, $op, $OpAssign, $op_assign, i32, u32, i64, u64, i128, u128 );
+    };
+    (@prim $Op:ident, $op:ident, $OpAssign:ident, $op_assign:ident, $($p:ty),*) => {$(
+        impl $Op<$p> for Integer {
+            type Output = Integer;
+            fn $op( self, other: $p ) -> Integer {
+                Integer( self.0.$op( other ) )
+            }
+        }
+        impl<'a> $Op<$p> for &'a Integer {
+            type Output = Incomplete<'a>;
+            fn $op( self, other: $p ) -> Incomplete<'a> {
+                Incomplete::new( (&self.0).$op( other ) )
+            }
+        }
+        impl $Op<Integer> for $p {
+            type Output = Integer;
+            fn $op( self, other: Integer ) -> Integer {
+                Integer( self.$op( other.0 ) )
+            }
+        }
+        impl<'a> $Op<&'a Integer> for $p {
+            type Output = Incomplete<'a>;
+            fn $op( self, other: &'a Integer ) -> Incomplete<'a> {
+                Incomplete::new( self.$op( &other.0 ) )
+            }
+        }
+        impl $OpAssign<$p> for Integer {
+            fn $op_assign( &mut self, other: $p ) {
+                self.0.$op_assign( other );
+            }
+        }
+    )*};
+}
+
+int_op!( Add, add, AddAssign, add_assign );
+int_op!( Sub, sub, SubAssign, sub_assign );
+int_op!( Mul, mul, MulAssign, mul_assign );
+int_op!( Div, div, DivAssign, div_assign );
+int_op!( Rem, rem, RemAssign, rem_assign );
+
+macro_rules! int_shift {
+    ($($p:ty),*) => {$(
+        impl Shl<$p> for Integer {
+            type Output = Integer;
+            fn shl( self, s: $p ) -> Integer {
+                Integer( self.0 << s )
+            }
+        }
+        impl<'a> Shl<$p> for &'a Integer {
+            type Output = Incomplete<'a>;
+            fn shl( self, s: $p ) -> Incomplete<'a> {
+                Incomplete::new( &self.0 << s )
+            }
+        }
+        impl Shr<$p> for Integer {
+            type Output = Integer;
+            fn shr( self, s: $p ) -> Integer {
+                Integer( self.0 >> s )
+            }
+        }
+        impl<'a> Shr<$p> for &'a Integer {
+            type Output = Incomplete<'a>;
+            fn shr( self, s: $p ) -> Incomplete<'a> {
+                Incomplete::new( &self.0 >> s )
+            }
+        }
+        impl ShlAssign<$p> for Integer {
+            fn shl_assign( &mut self, s: $p ) {
+                self.0 <<= s;
+            }
+        }
+        impl ShrAssign<$p> for Integer {
+            fn shr_assign( &mut self, s: $p ) {
+                self.0 >>= s;
+            }
+        }
+    )*};
+}
+
+int_shift!( i32, u32, usize );
+
+macro_rules! int_prim {
+    ($($p:ty),*) => {$(
+        impl From<$p> for Integer {
+            fn from( a: $p ) -> Self {
+                Integer( BigInt::from( a ) )
+            }
+        }
+        impl PartialEq<$p> for Integer {
+            fn eq( &self, other: &$p ) -> bool {
+                self.0 == BigInt::from( *other )
+            }
+        }
+        impl PartialOrd<$p> for Integer {
+            fn partial_cmp( &self, other: &$p ) -> Option<Ordering> {
+                self.0.partial_cmp( &BigInt::from( *other ) )
+            }
+        }
+        impl PartialEq<Integer> for $p {
+            fn eq( &self, other: &Integer ) -> bool {
+                BigInt::from( *self ) == other.0
+            }
+        }
+        impl PartialOrd<Integer> for $p {
+            fn partial_cmp( &self, other: &Integer ) -> Option<Ordering> {
+                BigInt::from( *self ).partial_cmp( &other.0 )
+            }
+        }
+    )*};
+}
+
+int_prim!( i32, u32, i64, u64, i128, u128, usize );
+
+impl std::iter::Sum for Integer {
+    fn sum<I: Iterator<Item = Integer>>( iter: I ) -> Integer {
+        iter.fold( Integer::new(), |s, x| s + x )
+    }
+}
@@ -0,0 +1,131 @@
+// Limits for the reductions of KLog<KFrac> and RegFrac<KFrac>, which otherwise never give up: for
+// values astronomically close to a digit boundary (or exactly on one, like a rational given by a
+// generator) the convergents may never agree on the next digit, and propagation goes on forever.
+
+use std::fmt::Display;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Debug, Default)]
+pub struct Cancel( Arc<AtomicBool> );
+
+impl Cancel {
+    // Cooperative cancellation: clones share the flag, so one can be handed to another thread
+    pub fn new() -> Self {
+        Cancel::default()
+    }
+    pub fn cancel( &self ) {
+        self.0.store( true, Ordering::Relaxed );
+    }
+    pub fn is_cancelled( &self ) -> bool {
+        self.0.load( Ordering::Relaxed )
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Budget {
+    // Checked between reduction steps, so a step (of 'ratio' propagations) may overshoot a little
+    pub convergents: Option<i128>, // highest convergent the remainder may reach
+    pub time: Option<Duration>,    // wall time of one call
+    pub bits: Option<u64>,         // size of the remainder (as in KLogOps::bits)
+    pub stall: Option<i128>,       // convergents spent in a row without a new digit (or term)
+    pub cancel: Option<Cancel>,
+}
+
+impl Budget {
+    pub fn new() -> Self {
+        // no limits at all, the old behaviour
+        Budget::default()
+    }
+    pub fn with_convergents( mut self, n: i128 ) -> Self {
+        self.convergents = Some(n);
+        self
+    }
+    pub fn with_time( mut self, t: Duration ) -> Self {
+        self.time = Some(t);
+        self
+    }
+    pub fn with_bits( mut self, b: u64 ) -> Self {
+        self.bits = Some(b);
+        self
+    }
+    pub fn with_stall( mut self, n: i128 ) -> Self {
+        self.stall = Some(n);
+        self
+    }
+    pub fn with_cancel( mut self, c: Cancel ) -> Self {
+        self.cancel = Some(c);
+        self
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum RedError {
+    BelowOne,          // the number is less than 1 (KLog only)
+    Infinite,          // both convergents are 1/0
+    Convergents(i128), // the convergent limit was reached
+    Time(Duration),    // out of time, after this long
+    Bits(u64),         // the remainder grew to this many bits
+    Stall(i128),       // no new digits since this convergent
+    Cancelled,
+    Io(std::io::ErrorKind), // writing to the sink failed
+    Precision(u32),         // an evaluation to this many bits is not possible
+}
+
+impl Display for RedError {
+    fn fmt( &self, f: &mut std::fmt::Formatter ) -> std::fmt::Result {
+        match self {
+            RedError::BelowOne => write!( f, "Can't reduce numbers (KFrac) less than 1!" ),
+            RedError::Infinite => write!( f, "Both convergents are infinite!" ),
+            RedError::Convergents(n) => write!( f, "Convergent limit reached at #{}", n ),
+            RedError::Time(t) => write!( f, "Out of time after {:.3}s", t.as_secs_f64() ),
+            RedError::Bits(b) => write!( f, "Remainder grew to {} bits", b ),
+            RedError::Stall(n) => write!( f, "Stalled, no new digits since convergent #{}", n ),
+            RedError::Cancelled => write!( f, "Cancelled" ),
+            RedError::Io(k) => write!( f, "Streaming the tape failed: {}", k ),
+            RedError::Precision(b) => write!( f, "Can't evaluate to {} bits", b ),
+        }
+    }
+}
+
+impl std::error::Error for RedError {}
+
+pub(crate) struct Meter<'a> {
+    // The running account of one reduction call against its budget
+    budget: &'a Budget,
+    start: Instant,
+    last: i128,
+}
+
+impl<'a> Meter<'a> {
+    pub(crate) fn new( budget: &'a Budget, n: i128 ) -> Self {
+        Meter { budget, start: Instant::now(), last: n }
+    }
+    pub(crate) fn progress( &mut self, n: i128 ) {
+        // a new digit (or term) came out at convergent n
+        self.last = n;
+    }
+    pub(crate) fn check( &self, n: i128, bits: u64 ) -> Result<(), RedError> {
+        let b = self.budget;
+        if b.cancel.as_ref().is_some_and( |c| c.is_cancelled() ) {
+            return Err( RedError::Cancelled );
+        }
+        if b.convergents.is_some_and( |m| n > m ) {
+            return Err( RedError::Convergents(n) );
+        }
+        if b.bits.is_some_and( |m| bits > m ) {
+            return Err( RedError::Bits(bits) );
+        }
+        if b.stall.is_some_and( |m| n - self.last > m ) {
+            return Err( RedError::Stall(self.last) );
+        }
+        if let Some(t) = b.time {
+            let e = self.start.elapsed();
+            if e > t {
+                return Err( RedError::Time(e) );
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,372 @@
+// C ABI (feature "capi"), the header k_log.h is generated in OUT_DIR by build.rs with cbindgen
+// (a copy is in include/), the library built with: cargo rustc --features capi --crate-type cdylib
+// All objects live behind opaque pointers made by the kl_*_new/from functions and released by the
+// matching kl_*_free. Strings returned to C are freed with kl_string_free. Null pointers are
+// tolerated everywhere (giving null, 0 or KL_ERR_NULL), and panics are caught at the boundary.
+
+use std::ffi::{CStr, CString, c_char};
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::time::Duration;
+
+use crate::{Budget, Frac, KFrac, KLog, RedError, RegFrac};
+
+pub const KL_OK: i32 = 0;
+pub const KL_DONE: i32 = 1;          // the expansion is finished (rationals)
+pub const KL_ERR_BELOW_ONE: i32 = -1;
+pub const KL_ERR_INFINITE: i32 = -2;
+pub const KL_ERR_CONVERGENTS: i32 = -3;
+pub const KL_ERR_TIME: i32 = -4;
+pub const KL_ERR_BITS: i32 = -5;
+pub const KL_ERR_STALL: i32 = -6;
+pub const KL_ERR_CANCELLED: i32 = -7;
+pub const KL_ERR_NULL: i32 = -8;
+pub const KL_ERR_PANIC: i32 = -9;
+pub const KL_ERR_IO: i32 = -10;
+pub const KL_ERR_ARG: i32 = -11;     // an argument out of range
+
+#[repr(C)]
+pub struct KlBudget {
+    // Limits of one reduction call, 0 for none
+    pub convergents: i64,
+    pub millis: u64,
+    pub bits: u64,
+    pub stall: i64,
+}
+
+pub enum KLogHandle {
+    Frac(KLog<Frac>),
+    KFrac(KLog<KFrac>),
+}
+
+pub enum RegFracHandle {
+    Frac(RegFrac<Frac>),
+    KFrac(RegFrac<KFrac>),
+}
+
+fn budget( b: *const KlBudget ) -> Budget {
+    let mut budget = Budget::new();
+    if let Some(b) = unsafe { b.as_ref() } {
+        if b.convergents > 0 { budget = budget.with_convergents( b.convergents as i128 ); }
+        if b.millis > 0 { budget = budget.with_time( Duration::from_millis( b.millis ) ); }
+        if b.bits > 0 { budget = budget.with_bits( b.bits ); }
+        if b.stall > 0 { budget = budget.with_stall( b.stall as i128 ); }
+    }
+    budget
+}
+
+fn code( r: Result<(), RedError> ) -> i32 {
+    match r {
+        Ok(()) => KL_OK,
+        Err(RedError::BelowOne) => KL_ERR_BELOW_ONE,
+        Err(RedError::Infinite) => KL_ERR_INFINITE,
+        Err(RedError::Convergents(_)) => KL_ERR_CONVERGENTS,
+        Err(RedError::Time(_)) => KL_ERR_TIME,
+        Err(RedError::Bits(_)) => KL_ERR_BITS,
+        Err(RedError::Stall(_)) => KL_ERR_STALL,
+        Err(RedError::Cancelled) => KL_ERR_CANCELLED,
+        Err(RedError::Io(_)) => KL_ERR_IO,
+        Err(RedError::Precision(_)) => KL_ERR_ARG,
+    }
+}
+
+fn guarded( f: impl FnOnce() -> i32 ) -> i32 {
+    catch_unwind( AssertUnwindSafe(f) ).unwrap_or( KL_ERR_PANIC )
+}
+
+fn boxed<T>( x: Option<T> ) -> *mut T {
+    x.map_or( std::ptr::null_mut(), |x| Box::into_raw( Box::new(x) ) )
+}
+
+fn c_string( s: String ) -> *mut c_char {
+    CString::new(s).map_or( std::ptr::null_mut(), CString::into_raw )
+}
+
+fn free<T>( p: *mut T ) {
+    if !p.is_null() {
+        drop( unsafe { Box::from_raw(p) } );
+    }
+}
+
+/// # Safety
+/// 's' must be null or a string from this library, not freed before.
+#[no_mangle]
+pub unsafe extern "C" fn kl_string_free( s: *mut c_char ) {
+    if !s.is_null() {
+        drop( CString::from_raw(s) );
+    }
+}
+
+// Frac
+
+#[no_mangle]
+pub extern "C" fn kl_frac_new( num: i64, den: i64 ) -> *mut Frac {
+    boxed( Some( Frac::from_i128( num as i128, den as i128 ) ) )
+}
+
+/// # Safety
+/// 's' must be null or a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn kl_frac_parse( s: *const c_char ) -> *mut Frac {
+    // decimals like "-1.2(34)e5" or "a/b", null if unparsable
+    if s.is_null() {
+        return std::ptr::null_mut();
+    }
+    boxed( CStr::from_ptr(s).to_str().ok().and_then( |s| s.parse::<Frac>().ok() ) )
+}
+
+/// # Safety
+/// 'f' must be null or a live Frac handle.
+#[no_mangle]
+pub unsafe extern "C" fn kl_frac_to_string( f: *const Frac ) -> *mut c_char {
+    match f.as_ref() {
+        Some(f) => c_string( f.to_string() ),
+        None => std::ptr::null_mut(),
+    }
+}
+
+/// # Safety
+/// 'f' must be null or a live Frac handle, which is invalid afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn kl_frac_free( f: *mut Frac ) {
+    free(f);
+}
+
+// KFrac
+
+/// # Safety
+/// 'name' must be null or a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn kl_kfrac_constant( name: *const c_char ) -> *mut KFrac {
+    // one of math_consts by name ("pi", "e", "zeta3", ...), null if unknown
+    if name.is_null() {
+        return std::ptr::null_mut();
+    }
+    boxed( CStr::from_ptr(name).to_str().ok().and_then( KFrac::by_name ) )
+}
+
+/// # Safety
+/// 'k' must be null or a live KFrac handle.
+#[no_mangle]
+pub unsafe extern "C" fn kl_kfrac_prop( k: *mut KFrac, count: i64 ) -> i32 {
+    // advance by 'count' >= 1 convergents (binary splitting)
+    match k.as_mut() {
+        Some(_) if count < 1 => KL_ERR_ARG,
+        Some(k) if count == 1 => guarded( || { k.prop(); KL_OK } ),
+        Some(k) => guarded( || { k.prop_bs( count as i128 ); KL_OK } ),
+        None => KL_ERR_NULL,
+    }
+}
+
+/// # Safety
+/// 'k' must be null or a live KFrac handle.
+#[no_mangle]
+pub unsafe extern "C" fn kl_kfrac_n( k: *const KFrac ) -> i64 {
+    k.as_ref().map_or( 0, |k| k.n as i64 )
+}
+
+/// # Safety
+/// 'k' must be null or a live KFrac handle, 'b' null or a valid budget, 'radius' null or writable.
+#[no_mangle]
+pub unsafe extern "C" fn kl_kfrac_evaluate( k: *mut KFrac, bits: u32, b: *const KlBudget, radius: *mut f64 ) -> *mut c_char {
+    // the correctly rounded value as a decimal string, and its error radius; null when 'bits' is
+    // 0 or the budget runs out first
+    let Some(k) = k.as_mut() else {
+        return std::ptr::null_mut();
+    };
+    let budget = budget(b);
+    match catch_unwind( AssertUnwindSafe( || k.evaluate( bits, &budget ) ) ) {
+        Ok(Ok((v, r))) => {
+            if let Some(radius) = radius.as_mut() {
+                *radius = r.to_f64();
+            }
+            c_string( v.to_string() )
+        },
+        _ => std::ptr::null_mut(),
+    }
+}
+
+/// # Safety
+/// 'k' must be null or a live KFrac handle, which is invalid afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn kl_kfrac_free( k: *mut KFrac ) {
+    free(k);
+}
+
+// KLog
+
+/// # Safety
+/// 'f' must be null or a live Frac handle, which is copied.
+#[no_mangle]
+pub unsafe extern "C" fn kl_klog_from_frac( f: *const Frac ) -> *mut KLogHandle {
+    boxed( f.as_ref().map( |f| KLogHandle::Frac( KLog::from( f.clone() ) ) ) )
+}
+
+/// # Safety
+/// 'k' must be null or a live KFrac handle, which is taken over (and invalid afterwards).
+#[no_mangle]
+pub unsafe extern "C" fn kl_klog_from_kfrac( k: *mut KFrac ) -> *mut KLogHandle {
+    if k.is_null() {
+        return std::ptr::null_mut();
+    }
+    boxed( Some( KLogHandle::KFrac( KLog::from( *Box::from_raw(k) ) ) ) )
+}
+
+/// # Safety
+/// 'h' must be null or a live KLog handle, 'b' null or a valid budget.
+#[no_mangle]
+pub unsafe extern "C" fn kl_klog_red( h: *mut KLogHandle, digits: usize, b: *const KlBudget ) -> i32 {
+    // up to 'digits' new digits, KL_DONE once a rational is fully expanded
+    let Some(h) = h.as_mut() else {
+        return KL_ERR_NULL;
+    };
+    let budget = budget(b);
+    guarded( || match h {
+        KLogHandle::Frac(x) => {
+            let limit = x.tape.len() + digits;
+            if !x.remainder.atleast1() {
+                return KL_ERR_BELOW_ONE;
+            }
+            while x.tape.len() < limit {
+                if !x.remainder.finite() {
+                    x.done = true;
+                    return KL_DONE;
+                }
+                x.red1();
+            }
+            KL_OK
+        },
+        KLogHandle::KFrac(x) => code( x.try_red( digits, None, false, &budget ) ),
+    })
+}
+
+/// # Safety
+/// 'h' must be null or a live KLog handle.
+#[no_mangle]
+pub unsafe extern "C" fn kl_klog_len( h: *const KLogHandle ) -> usize {
+    match h.as_ref() {
+        Some(KLogHandle::Frac(x)) => x.tape.len(),
+        Some(KLogHandle::KFrac(x)) => x.tape.len(),
+        None => 0,
+    }
+}
+
+/// # Safety
+/// 'h' must be null or a live KLog handle, 'out' null or writable for 'cap' bytes.
+#[no_mangle]
+pub unsafe extern "C" fn kl_klog_tape( h: *const KLogHandle, out: *mut u8, cap: usize ) -> usize {
+    // copies up to 'cap' digits (0 or 1) into 'out', returns the full length of the tape
+    let tape = match h.as_ref() {
+        Some(KLogHandle::Frac(x)) => &x.tape,
+        Some(KLogHandle::KFrac(x)) => &x.tape,
+        None => return 0,
+    };
+    if !out.is_null() {
+        let m = cap.min( tape.len() );
+        std::ptr::copy_nonoverlapping( tape.as_ptr(), out, m );
+    }
+    tape.len()
+}
+
+/// # Safety
+/// 'h' must be null or a live KLog handle.
+#[no_mangle]
+pub unsafe extern "C" fn kl_klog_done( h: *const KLogHandle ) -> bool {
+    matches!( h.as_ref(), Some(KLogHandle::Frac(x)) if x.done || !x.remainder.finite() )
+}
+
+/// # Safety
+/// 'h' must be null or a live KLog handle.
+#[no_mangle]
+pub unsafe extern "C" fn kl_klog_to_string( h: *const KLogHandle ) -> *mut c_char {
+    match h.as_ref() {
+        Some(KLogHandle::Frac(x)) => c_string( x.to_string() ),
+        Some(KLogHandle::KFrac(x)) => c_string( x.to_string() ),
+        None => std::ptr::null_mut(),
+    }
+}
+
+/// # Safety
+/// 'h' must be null or a live KLog handle, which is invalid afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn kl_klog_free( h: *mut KLogHandle ) {
+    free(h);
+}
+
+// RegFrac
+
+/// # Safety
+/// 'f' must be null or a live Frac handle, which is copied.
+#[no_mangle]
+pub unsafe extern "C" fn kl_regfrac_from_frac( f: *const Frac ) -> *mut RegFracHandle {
+    boxed( f.as_ref().map( |f| RegFracHandle::Frac( RegFrac::from( f.clone() ) ) ) )
+}
+
+/// # Safety
+/// 'k' must be null or a live KFrac handle, which is taken over (and invalid afterwards).
+#[no_mangle]
+pub unsafe extern "C" fn kl_regfrac_from_kfrac( k: *mut KFrac ) -> *mut RegFracHandle {
+    if k.is_null() {
+        return std::ptr::null_mut();
+    }
+    boxed( Some( RegFracHandle::KFrac( RegFrac::from( *Box::from_raw(k) ) ) ) )
+}
+
+/// # Safety
+/// 'h' must be null or a live RegFrac handle, 'b' null or a valid budget.
+#[no_mangle]
+pub unsafe extern "C" fn kl_regfrac_red( h: *mut RegFracHandle, terms: usize, b: *const KlBudget ) -> i32 {
+    // up to 'terms' new terms, KL_DONE once a rational is fully expanded
+    let Some(h) = h.as_mut() else {
+        return KL_ERR_NULL;
+    };
+    let budget = budget(b);
+    guarded( || match h {
+        RegFracHandle::Frac(x) => {
+            let limit = x.tape.len() + terms;
+            while x.tape.len() < limit {
+                if !x.remainder.finite() {
+                    return KL_DONE;
+                }
+                x.red1();
+            }
+            KL_OK
+        },
+        RegFracHandle::KFrac(x) => code( x.try_red( terms, None, &budget ) ),
+    })
+}
+
+/// # Safety
+/// 'h' must be null or a live RegFrac handle.
+#[no_mangle]
+pub unsafe extern "C" fn kl_regfrac_len( h: *const RegFracHandle ) -> usize {
+    match h.as_ref() {
+        Some(RegFracHandle::Frac(x)) => x.tape.len(),
+        Some(RegFracHandle::KFrac(x)) => x.tape.len(),
+        None => 0,
+    }
+}
+
+/// # Safety
+/// 'h' must be null or a live RegFrac handle, 'out' null or writable for 'cap' terms.
+#[no_mangle]
+pub unsafe extern "C" fn kl_regfrac_terms( h: *const RegFracHandle, out: *mut u64, cap: usize ) -> usize {
+    // copies up to 'cap' terms into 'out' (saturated at UINT64_MAX), returns the number of terms
+    let tape = match h.as_ref() {
+        Some(RegFracHandle::Frac(x)) => &x.tape,
+        Some(RegFracHandle::KFrac(x)) => &x.tape,
+        None => return 0,
+    };
+    if !out.is_null() {
+        for (i, t) in tape.iter().take(cap).enumerate() {
+            *out.add(i) = u64::try_from(*t).unwrap_or( u64::MAX );
+        }
+    }
+    tape.len()
+}
+
+/// # Safety
+/// 'h' must be null or a live RegFrac handle, which is invalid afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn kl_regfrac_free( h: *mut RegFracHandle ) {
+    free(h);
+}
@@ -0,0 +1,351 @@
+// Lazy real arithmetic: expression trees whose leaves are rationals and KFracs (the math_consts, or
+// any generator) and whose nodes are Gosper's homographic (Mobius) and bihomographic maps
+//   (a x y + b x + c y + d)/(e x y + f x + g y + h).
+// n-th roots (Root) are nodes as well. An Expr is a Real, so it is expanded with red_real through
+// KLog::from( Mobius::from(expr) ), or expr.klog(). The precision is managed by the nodes: each refinement goes to the operand that
+// contributes more to the width of the enclosure, and only the leaves ever propagate. Rationals
+// are folded into the nodes as they are built, e.g. x + 1/2 is the homographic (2 x + 1)/2.
+
+use std::borrow::Cow;
+use std::fmt::Display;
+use std::ops::{Add, Div, Mul, Neg, Sub};
+use crate::int::Integer;
+use crate::fractions::{Frac, KFrac, RegFrac, RegFracOps};
+use crate::logs::{KLog, KLogOps};
+use crate::real::{self, Mobius, Real, upright, uprights};
+use crate::root::Root;
+
+#[derive(Clone)]
+pub struct Bihom<X: Real, Y: Real> {
+    // (a x y + b x + c y + d)/(e x y + f x + g y + h) of the Reals x and y, with t = [a, b, ..., h].
+    // As for Mobius, the enclosures of x and y are kept and only taken again after refinement.
+    pub t: [Integer; 8],
+    x: X,
+    y: Y,
+    xs: (Frac, Frac),
+    ys: (Frac, Frac),
+}
+
+impl<X: Real, Y: Real> Bihom<X, Y> {
+    pub fn new( x: X, y: Y, t: [i128; 8] ) -> Self {
+        let (xs, ys) = ( uprights( &x ), uprights( &y ) );
+        Bihom { t: t.map( Integer::from ), x, y, xs, ys }
+    }
+    pub fn inner( &self ) -> (&X, &Y) {
+        (&self.x, &self.y)
+    }
+
+    fn span( &self, xs: [&Frac; 2], ys: [&Frac; 2] ) -> Option<(Frac, Frac)> {
+        // The image of the box xs * ys (ends with positive denominators), None when unbounded: an
+        // infinite end, or the denominator vanishing in the box. Being bilinear, the numerator and
+        // denominator are extreme at the corners, so are the fractions where the latter keeps its sign.
+        let t = &self.t;
+        let mut lo: Option<Frac> = None;
+        let mut hi: Option<Frac> = None;
+        let mut sign = None;
+        for x in xs {
+            for y in ys {
+                if !x.finite() || !y.finite() {
+                    return None;
+                }
+                let (xy, xd) = ( Integer::from( &x.num * &y.num ), Integer::from( &x.num * &y.den ) );
+                let (dy, dd) = ( Integer::from( &x.den * &y.num ), Integer::from( &x.den * &y.den ) );
+                let den = Integer::from( &t[4] * &xy ) + &t[5] * &xd + &t[6] * &dy + &t[7] * &dd;
+                if den == 0 || *sign.get_or_insert( den < 0 ) != (den < 0) {
+                    return None;
+                }
+                let num = Integer::from( &t[0] * &xy ) + &t[1] * &xd + &t[2] * &dy + &t[3] * &dd;
+                let z = Frac::from_int( num, den );
+                if lo.as_ref().is_none_or( |l| z < *l ) {
+                    lo = Some( z.clone() );
+                }
+                if hi.as_ref().is_none_or( |h| z > *h ) {
+                    hi = Some( z );
+                }
+            }
+        }
+        Some(( lo?, hi? ))
+    }
+
+    fn point( &self, x: &Frac, y: &Frac ) -> Frac {
+        // the exact value, for exact operands (n/0 when the denominator vanishes)
+        let t = &self.t;
+        let (xy, xd) = ( Integer::from( &x.num * &y.num ), Integer::from( &x.num * &y.den ) );
+        let (dy, dd) = ( Integer::from( &x.den * &y.num ), Integer::from( &x.den * &y.den ) );
+        Frac::from_int( Integer::from( &t[0] * &xy ) + &t[1] * &xd + &t[2] * &dy + &t[3] * &dd,
+                        Integer::from( &t[4] * &xy ) + &t[5] * &xd + &t[6] * &dy + &t[7] * &dd )
+    }
+}
+
+fn width( s: Option<(Frac, Frac)> ) -> Option<Frac> {
+    // hi - lo, not reduced as it is only compared
+    s.map( |(lo, hi)| {
+        let (lo, hi) = ( upright( Cow::Owned(lo) ), upright( Cow::Owned(hi) ) );
+        Frac::from_int( Integer::from( &hi.num * &lo.den ) - &lo.num * &hi.den, Integer::from( &hi.den * &lo.den ) )
+    })
+}
+
+impl<X: Real, Y: Real> Real for Bihom<X, Y> {
+    fn enclosure( &self ) -> (Cow<'_, Frac>, Cow<'_, Frac>) {
+        let ((x1, x2), (y1, y2)) = (&self.xs, &self.ys);
+        if self.exact() {
+            let z = self.point( x1, y1 );
+            return ( Cow::Owned( z.clone() ), Cow::Owned(z) );
+        }
+        match self.span( [x1, x2], [y1, y2] ) {
+            Some((lo, hi)) => ( Cow::Owned(lo), Cow::Owned(hi) ),
+            None => ( Cow::Owned( Frac::from_i128(1, 0) ), Cow::Owned( Frac::from_i128(-1, 0) ) ),
+        }
+    }
+    fn refine( &mut self ) {
+        self.refine_by( 1 );
+    }
+    fn refine_by( &mut self, k: usize ) {
+        // The operand contributing more to the width, measured with the other one pinned to an end.
+        // Unbounded contributions count as the widest, and both are refined when both are.
+        let (rx, ry) = match (self.x.exact(), self.y.exact()) {
+            (true, true) => (false, false),
+            (true, false) => (false, true),
+            (false, true) => (true, false),
+            (false, false) => {
+                let ((x1, x2), (y1, y2)) = (&self.xs, &self.ys);
+                let wx = width( self.span( [x1, x2], [y1, y1] ) );
+                let wy = width( self.span( [x1, x1], [y1, y2] ) );
+                match (wx, wy) {
+                    (None, None) => (true, true),
+                    (None, Some(_)) => (true, false),
+                    (Some(_), None) => (false, true),
+                    (Some(wx), Some(wy)) => (wx >= wy, wx < wy),
+                }
+            },
+        };
+        if rx {
+            self.x.refine_by( k );
+            self.xs = uprights( &self.x );
+        }
+        if ry {
+            self.y.refine_by( k );
+            self.ys = uprights( &self.y );
+        }
+    }
+    fn steps( &self ) -> i128 {
+        self.x.steps() + self.y.steps()
+    }
+    fn exact( &self ) -> bool {
+        self.x.exact() && self.y.exact()
+    }
+}
+
+impl<X: Real, Y: Real> KLogOps for Bihom<X, Y> {
+    fn iota( &mut self ) {
+        real::iota_rows( &mut self.t );
+    }
+    fn theta( &mut self ) {
+        real::theta_rows( &mut self.t, 1 );
+    }
+    fn theta_run( &mut self, s: u32 ) {
+        real::theta_rows( &mut self.t, s );
+    }
+    fn gcd_div( &mut self ) {
+        real::gcd_rows( &mut self.t );
+    }
+    fn pow2_div( &mut self ) {
+        real::pow2_rows( &mut self.t );
+    }
+    fn bits( &self ) -> u64 {
+        real::bits_rows( &self.t )
+    }
+}
+
+impl<X: Real, Y: Real> RegFracOps for Bihom<X, Y> {
+    fn term( &mut self, t: &Integer ) {
+        real::left( &mut self.t, [ Integer::from(0), Integer::from(1), Integer::from(1), Integer::from( -t ) ] );
+    }
+}
+
+#[derive(Clone)]
+pub enum Expr {
+    Rat(Frac),
+    Const(KFrac),
+    Hom(Box<Mobius<Expr>>),
+    Bihom(Box<Bihom<Expr, Expr>>),
+    Root(Box<Root<Expr>>),
+}
+
+impl Expr {
+    pub fn constant( name: &str ) -> Option<Expr> {
+        // one of math_consts ("pi", "e", "sqrt2", ...)
+        Some( Expr::Const( KFrac::by_name( name )? ) )
+    }
+
+    pub fn hom( self, m: [i128; 4] ) -> Expr {
+        // (a x + b)/(c x + d), m = [a, b, c, d]
+        Expr::fold( self, m.map( Integer::from ) )
+    }
+
+    pub fn bihom( self, y: Expr, t: [i128; 8] ) -> Expr {
+        // (a x y + b x + c y + d)/(e x y + f x + g y + h), t = [a, b, ..., h], with rational operands
+        // substituted into the coefficients
+        match (self, y) {
+            (x, Expr::Rat(q)) => {
+                let [a, b, c, d, e, f, g, h] = t.map( Integer::from );
+                let lin = |u: &Integer, v: &Integer| Integer::from( u * &q.num ) + v * &q.den;
+                Expr::fold( x, [ lin(&a, &b), lin(&c, &d), lin(&e, &f), lin(&g, &h) ] )
+            },
+            (Expr::Rat(p), y) => {
+                let [a, b, c, d, e, f, g, h] = t.map( Integer::from );
+                let lin = |u: &Integer, v: &Integer| Integer::from( u * &p.num ) + v * &p.den;
+                Expr::fold( y, [ lin(&a, &c), lin(&b, &d), lin(&e, &g), lin(&f, &h) ] )
+            },
+            (x, y) => Expr::Bihom( Box::new( Bihom::new( x, y, t ) ) ),
+        }
+    }
+
+    pub fn sqrt( self ) -> Expr {
+        Expr::Root( Box::new( Root::sqrt( self ) ) )
+    }
+    pub fn root( self, n: u32 ) -> Option<Expr> {
+        // x^(1/n), for x >= 0; None for n = 0
+        Some( Expr::Root( Box::new( Root::new( self, n )? ) ) )
+    }
+
+    fn fold( x: Expr, m: [Integer; 4] ) -> Expr {
+        // hom with big coefficients: evaluated for rationals, composed for homographic nodes
+        match x {
+            Expr::Rat(x) => {
+                let [a, b, c, d] = &m;
+                let mut z = Frac::from_int( Integer::from( a * &x.num ) + b * &x.den, Integer::from( c * &x.num ) + d * &x.den );
+                z.normalise();
+                Expr::Rat(z)
+            },
+            Expr::Hom(mut h) => {
+                real::left( &mut h.m, m );
+                Expr::Hom(h)
+            },
+            x => Expr::Hom( Box::new( Mobius::with_int( x, m ) ) ),
+        }
+    }
+
+    pub fn klog( self ) -> KLog<Mobius<Expr>> {
+        KLog::from( Mobius::from( self ) )
+    }
+    pub fn reg_frac( self ) -> RegFrac<Mobius<Expr>> {
+        RegFrac::from( Mobius::from( self ) )
+    }
+}
+
+impl Real for Expr {
+    fn enclosure( &self ) -> (Cow<'_, Frac>, Cow<'_, Frac>) {
+        match self {
+            Expr::Rat(x) => x.enclosure(),
+            Expr::Const(k) => k.enclosure(),
+            Expr::Hom(h) => h.enclosure(),
+            Expr::Bihom(b) => b.enclosure(),
+            Expr::Root(r) => r.enclosure(),
+        }
+    }
+    fn refine( &mut self ) {
+        self.refine_by( 1 );
+    }
+    fn refine_by( &mut self, k: usize ) {
+        match self {
+            Expr::Rat(_) => {},
+            Expr::Const(c) => c.refine_by( k ),
+            Expr::Hom(h) => h.refine_by( k ),
+            Expr::Bihom(b) => b.refine_by( k ),
+            Expr::Root(r) => r.refine_by( k ),
+        }
+    }
+    fn steps( &self ) -> i128 {
+        match self {
+            Expr::Rat(_) => 0,
+            Expr::Const(k) => k.n,
+            Expr::Hom(h) => h.steps(),
+            Expr::Bihom(b) => b.steps(),
+            Expr::Root(r) => r.steps(),
+        }
+    }
+    fn exact( &self ) -> bool {
+        match self {
+            Expr::Rat(_) => true,
+            Expr::Const(_) => false,
+            Expr::Hom(h) => h.exact(),
+            Expr::Bihom(b) => b.exact(),
+            Expr::Root(r) => r.exact(),
+        }
+    }
+}
+
+const ADD: [i128; 8] = [0, 1, 1, 0, 0, 0, 0, 1];
+const SUB: [i128; 8] = [0, 1, -1, 0, 0, 0, 0, 1];
+const MUL: [i128; 8] = [1, 0, 0, 0, 0, 0, 0, 1];
+const DIV: [i128; 8] = [0, 1, 0, 0, 0, 0, 1, 0];
+
+impl Display for Expr {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        // the operators as such, other nodes with their coefficients, e.g. hom[2, 1, 0, 2](pi)
+        match self {
+            Expr::Rat(x) => write!( f, "{}", x ),
+            Expr::Const(k) => write!( f, "{}", k.name.unwrap_or("KFrac") ),
+            Expr::Hom(h) => {
+                let [a, b, c, d] = &h.m;
+                write!( f, "hom[{}, {}, {}, {}]({})", a, b, c, d, h.inner() )
+            },
+            Expr::Bihom(t) => {
+                let (x, y) = t.inner();
+                let op = [(ADD, "+"), (SUB, "-"), (MUL, "*"), (DIV, "/")].into_iter()
+                    .find( |(c, _)| t.t.iter().zip( c ).all( |(u, v)| *u == *v ) );
+                match op {
+                    Some((_, op)) => write!( f, "({} {} {})", x, op, y ),
+                    None => {
+                        let c = &t.t;
+                        write!( f, "bihom[{}, {}, {}, {}, {}, {}, {}, {}]({}, {})", c[0], c[1], c[2], c[3], c[4], c[5], c[6], c[7], x, y )
+                    },
+                }
+            },
+            Expr::Root(r) if r.n == 2 => write!( f, "sqrt({})", r.inner() ),
+            Expr::Root(r) => write!( f, "root{}({})", r.n, r.inner() ),
+        }
+    }
+}
+
+impl From<Frac> for Expr {
+    fn from( x: Frac ) -> Self {
+        Expr::Rat(x)
+    }
+}
+
+impl From<KFrac> for Expr {
+    fn from( k: KFrac ) -> Self {
+        Expr::Const(k)
+    }
+}
+
+impl From<i128> for Expr {
+    fn from( x: i128 ) -> Self {
+        Expr::Rat( Frac::from(x) )
+    }
+}
+
+macro_rules! expr_op {
+    ($Op:ident, $op:ident, $t:expr) => {
+        impl<T: Into<Expr>> $Op<T> for Expr {
+            type Output = Expr;
+            fn $op( self, other: T ) -> Expr {
+                self.bihom( other.into(), $t )
+            }
+        }
+    };
+}
+
+expr_op!( Add, add, ADD );
+expr_op!( Sub, sub, SUB );
+expr_op!( Mul, mul, MUL );
+expr_op!( Div, div, DIV );
+
+impl Neg for Expr {
+    type Output = Expr;
+    fn neg( self ) -> Expr {
+        self.hom( [-1, 0, 0, 1] )
+    }
+}
@@ -1,9 +1,22 @@
 // Ordinary and continued fractions
 
-use rug::{Integer, Float, Complete};
+use crate::int::{Integer, IntOps, Pow};
+#[cfg(feature = "gmp")]
+use rug::{Float, Rational, float::Round};
+use std::borrow::Cow;
+use std::cmp::Ordering;
 use std::fmt::Display;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+use crate::norm::{Norm, Normaliser};
+use crate::math_consts;
+use crate::budget::{Budget, Meter, RedError};
+use crate::progress::{Observer, Stage, Watch, report};
+use crate::stream::{self, Sink};
+use std::time::Instant;
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug)]
 pub struct Frac {
     pub num: Integer,
     pub den: Integer,
@@ -16,6 +29,7 @@ impl Frac {
     pub fn from_int(x: Integer, y: Integer) -> Self {
         Self {num: x, den: y}
     }
+    #[cfg(feature = "gmp")]
     pub fn float (&self, prec: u32) -> Float {
         Float::with_val(prec, &self.num)/&self.den 
     }
@@ -24,10 +38,23 @@ impl Frac {
     }
     pub fn gcd_div( &mut self ) {
         // reduce the fraction via gcd
-        let g = self.num.gcd_ref( &self.den ).complete();
+        let g = self.num.gcd_of( &self.den );
         self.num /= &g;
         self.den /= &g;
     }
+    pub fn pow2_div( &mut self ) {
+        // strip the common powers of two only, much cheaper than gcd_div
+        let z = match (self.num.find_one(0), self.den.find_one(0)) {
+            (Some(z1), Some(z2)) => z1.min(z2),
+            (Some(z), None) | (None, Some(z)) => z,
+            (None, None) => 0,
+        };
+        self.num >>= z;
+        self.den >>= z;
+    }
+    pub fn bits( &self ) -> u64 {
+        self.num.significant_bits() as u64 + self.den.significant_bits() as u64
+    }
     pub fn atleast1( &self ) -> bool {
         if self.den >= 0 { // We still want inf >= 1
             self.den >= 0 && self.num >= self.den
@@ -45,8 +72,207 @@ impl Frac {
     pub fn finite( &self ) -> bool {
         self.den != 0
     }
+    pub fn log2_floor( &self ) -> Option<u32> {
+        // floor(log_2 |x|) for |x| >= 1 from the bit lengths, None for 1/0
+        if !self.finite() {
+            return None;
+        }
+        let s = self.num.significant_bits().saturating_sub( self.den.significant_bits() );
+        if s > 0 && self.num.cmp_abs( &(Integer::from(&self.den) << s) ) == Ordering::Less {
+            Some(s - 1)
+        } else {
+            Some(s)
+        }
+    }
+    pub fn simplest( x: &Frac, y: &Frac ) -> Frac {
+        // The rational with the smallest denominator in the closed interval between two
+        // non-negative fractions (1/0 allowed), from their common continued fraction prefix
+        let (mut ln, mut ld, mut hn, mut hd) = if Integer::from(&x.num * &y.den) <= Integer::from(&y.num * &x.den) {
+            (x.num.clone(), x.den.clone(), y.num.clone(), y.den.clone())
+        } else {
+            (y.num.clone(), y.den.clone(), x.num.clone(), x.den.clone())
+        };
+        // Accumulated convergents | p1 p0 |
+        //                         | q1 q0 |
+        let (mut p0, mut q0) = (Integer::from(0), Integer::from(1));
+        let (mut p1, mut q1) = (Integer::from(1), Integer::from(0));
+        loop {
+            if ld == 0 {
+                return Frac::from_int(p1, q1);
+            }
+            let (n, r) = ln.div_rem_floor_of(&ld);
+            let c = if r == 0 { n.clone() } else { Integer::from(&n + 1) };
+            if hd == 0 || Integer::from(&c * &hd) <= hn {
+                let mut z = Frac::from_int(c.clone() * &p1 + p0, c * &q1 + q0);
+                z.gcd_div();
+                return z;
+            }
+            // lo and hi share the integer part n, continue with 1/(hi-n) and 1/(lo-n)
+            hn -= Integer::from(&n * &hd);
+            ln = r;
+            (ln, ld, hn, hd) = (hd, hn, ld, ln);
+            p0 += Integer::from(&n * &p1);
+            q0 += Integer::from(&n * &q1);
+            std::mem::swap( &mut p0, &mut p1 );
+            std::mem::swap( &mut q0, &mut q1 );
+        }
+    }
+}
+
+impl Frac {
+    // Frac as a rational number in its own right. The reductions keep fractions unreduced and allow
+    // negative denominators for speed; the arithmetic below returns canonical fractions instead.
+
+    pub fn normalise( &mut self ) {
+        // canonical form: positive (or zero) denominator and lowest terms, 1/0 and -1/0 for infinities
+        if self.den < 0 {
+            self.num = -std::mem::take( &mut self.num );
+            self.den = -std::mem::take( &mut self.den );
+        }
+        let g = self.num.gcd_of( &self.den );
+        if g > 1 {
+            self.num /= &g;
+            self.den /= &g;
+        }
+    }
+    // None for the infinities (and 0/0), which have no integer part
+    pub fn floor( &self ) -> Option<Integer> {
+        self.finite().then( || self.num.div_rem_floor_of( &self.den ).0 )
+    }
+    pub fn ceil( &self ) -> Option<Integer> {
+        self.finite().then( || self.num.div_rem_ceil_of( &self.den ).0 )
+    }
+    pub fn round( &self ) -> Option<Integer> {
+        // to the nearest integer, ties away from zero
+        self.finite().then( || self.num.div_rem_round_of( &self.den ).0 )
+    }
+    pub fn abs( &self ) -> Frac {
+        Frac::from_int( self.num.abs_of(), self.den.abs_of() )
+    }
+    pub fn pow( &self, e: i32 ) -> Frac {
+        let mut z = Frac::from_int( self.num.pow_of( e.unsigned_abs() ), self.den.pow_of( e.unsigned_abs() ) );
+        if e < 0 {
+            z.inv();
+        }
+        z.normalise();
+        z
+    }
 }
 
+impl Ord for Frac {
+    fn cmp( &self, other: &Self ) -> Ordering {
+        // 0/0 has no value, it is put below everything else (-1/0 included) to keep the order total
+        let nan = |x: &Frac| x.num == 0 && x.den == 0;
+        match (nan(self), nan(other)) {
+            (true, true) => return Ordering::Equal,
+            (true, false) => return Ordering::Less,
+            (false, true) => return Ordering::Greater,
+            _ => {}
+        }
+        match (self.finite(), other.finite()) {
+            (false, false) => self.num.cmp0().cmp( &other.num.cmp0() ),
+            (false, true) => if self.num > 0 { Ordering::Greater } else { Ordering::Less },
+            (true, false) => if other.num > 0 { Ordering::Less } else { Ordering::Greater },
+            (true, true) => {
+                // cross multiplication, flipped when exactly one denominator is negative
+                let ord = Integer::from( &self.num * &other.den ).cmp( &Integer::from( &other.num * &self.den ) );
+                if (self.den < 0) != (other.den < 0) { ord.reverse() } else { ord }
+            },
+        }
+    }
+}
+
+impl PartialOrd for Frac {
+    fn partial_cmp( &self, other: &Self ) -> Option<Ordering> {
+        Some( self.cmp( other ) )
+    }
+}
+
+impl PartialEq for Frac {
+    fn eq( &self, other: &Self ) -> bool {
+        self.cmp( other ) == Ordering::Equal
+    }
+}
+
+impl Eq for Frac {}
+
+impl Neg for Frac {
+    type Output = Frac;
+    fn neg( mut self ) -> Frac {
+        self.num = -self.num;
+        self
+    }
+}
+
+impl Neg for &Frac {
+    type Output = Frac;
+    fn neg( self ) -> Frac {
+        -self.clone()
+    }
+}
+
+fn frac_add( x: &Frac, y: &Frac ) -> Frac {
+    Frac::from_int( Integer::from( &x.num * &y.den ) + &y.num * &x.den, Integer::from( &x.den * &y.den ) )
+}
+
+fn frac_sub( x: &Frac, y: &Frac ) -> Frac {
+    Frac::from_int( Integer::from( &x.num * &y.den ) - &y.num * &x.den, Integer::from( &x.den * &y.den ) )
+}
+
+fn frac_mul( x: &Frac, y: &Frac ) -> Frac {
+    Frac::from_int( Integer::from( &x.num * &y.num ), Integer::from( &x.den * &y.den ) )
+}
+
+fn frac_div( x: &Frac, y: &Frac ) -> Frac {
+    Frac::from_int( Integer::from( &x.num * &y.den ), Integer::from( &x.den * &y.num ) )
+}
+
+macro_rules! frac_op {
+    ($Op:ident, $op:ident, $OpAssign:ident, $op_assign:ident, $f:ident) => {
+        impl $Op<&Frac> for &Frac {
+            type Output = Frac;
+            fn $op( self, other: &Frac ) -> Frac {
+                let mut z = $f( self, other );
+                z.normalise();
+                z
+            }
+        }
+        impl $Op<Frac> for &Frac {
+            type Output = Frac;
+            fn $op( self, other: Frac ) -> Frac {
+                self.$op( &other )
+            }
+        }
+        impl $Op<&Frac> for Frac {
+            type Output = Frac;
+            fn $op( self, other: &Frac ) -> Frac {
+                (&self).$op( other )
+            }
+        }
+        impl $Op<Frac> for Frac {
+            type Output = Frac;
+            fn $op( self, other: Frac ) -> Frac {
+                (&self).$op( &other )
+            }
+        }
+        impl $OpAssign<&Frac> for Frac {
+            fn $op_assign( &mut self, other: &Frac ) {
+                *self = (&*self).$op( other );
+            }
+        }
+        impl $OpAssign<Frac> for Frac {
+            fn $op_assign( &mut self, other: Frac ) {
+                *self = (&*self).$op( &other );
+            }
+        }
+    };
+}
+
+frac_op!( Add, add, AddAssign, add_assign, frac_add );
+frac_op!( Sub, sub, SubAssign, sub_assign, frac_sub );
+frac_op!( Mul, mul, MulAssign, mul_assign, frac_mul );
+frac_op!( Div, div, DivAssign, div_assign, frac_div );
+
 impl From<i128> for Frac {
     fn from( a: i128 ) -> Self {
         Frac::from_i128(a, 1)
@@ -59,6 +285,149 @@ impl From<Integer> for Frac {
     }
 }
 
+#[cfg(feature = "gmp")]
+impl From<Rational> for Frac {
+    fn from( a: Rational ) -> Self {
+        let (num, den) = a.into_numer_denom();
+        Frac::from_int(num, den)
+    }
+}
+
+#[cfg(feature = "gmp")]
+impl From<&Rational> for Frac {
+    fn from( a: &Rational ) -> Self {
+        Frac::from_int(a.numer().clone(), a.denom().clone())
+    }
+}
+
+impl TryFrom<f64> for Frac {
+    type Error = FracError;
+    fn try_from( a: f64 ) -> Result<Self, FracError> {
+        // exact, every finite f64 is a dyadic rational; infinities become 1/0 and -1/0
+        if a.is_nan() {
+            Err(FracError::NaN)
+        } else if a.is_infinite() {
+            Ok(Frac::from_i128( a.signum() as i128, 0 ))
+        } else {
+            // a = m 2^e with the 53 bit mantissa m (52 for subnormals)
+            let bits = a.to_bits();
+            let (m, e) = match ((bits >> 52) & 0x7ff) as i32 {
+                0 => (bits & ((1 << 52) - 1), -1074),
+                e => ((bits & ((1 << 52) - 1)) | (1 << 52), e - 1075),
+            };
+            let num = if a < 0.0 { -Integer::from(m) } else { Integer::from(m) };
+            let mut z = if e >= 0 {
+                Frac::from_int( num << e as u32, Integer::from(1) )
+            } else {
+                Frac::from_int( num, Integer::from(1) << e.unsigned_abs() )
+            };
+            z.normalise();
+            Ok(z)
+        }
+    }
+}
+
+#[cfg(feature = "gmp")]
+impl TryFrom<&Float> for Frac {
+    type Error = FracError;
+    fn try_from( a: &Float ) -> Result<Self, FracError> {
+        // exact at the full precision of 'a'; infinities become 1/0 and -1/0
+        if a.is_nan() {
+            Err(FracError::NaN)
+        } else if a.is_infinite() {
+            Ok(Frac::from_i128( if a.is_sign_negative() { -1 } else { 1 }, 0 ))
+        } else {
+            a.to_rational().map( Frac::from ).ok_or(FracError::NaN)
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum FracError {
+    Parse(String), // input not recognised as a fraction
+    NaN,           // a float without a value
+    Exponent(i32), // a decimal exponent beyond MAX_EXP
+}
+
+impl Display for FracError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            FracError::Parse(s) => write!( f, "Unrecognized fraction '{}'", s ),
+            FracError::NaN => write!( f, "NaN has no fraction" ),
+            FracError::Exponent(e) => write!( f, "Exponent {} is out of range (at most {} in size)", e, MAX_EXP ),
+        }
+    }
+}
+
+impl std::error::Error for FracError {}
+
+// decimal exponents allowed in parsing, as 10^exp is spelled out
+const MAX_EXP: i32 = 1 << 20;
+
+fn parse_decimal( s: &str ) -> Result<Frac, FracError> {
+    // [sign] digits [. digits [(repeating digits)]] [e [sign] digits], e.g. "-1.5e-7" or "0.(142857)"
+    let bad = || FracError::Parse( s.to_string() );
+    let (neg, s) = match s.strip_prefix('-') {
+        Some(t) => (true, t),
+        None => (false, s.strip_prefix('+').unwrap_or(s)),
+    };
+    let (mant, exp) = match s.find(['e', 'E']) {
+        Some(i) => (&s[..i], s[i+1..].parse::<i32>().map_err( |_| bad() )?),
+        None => (s, 0),
+    };
+    let (int, frac) = mant.split_once('.').unwrap_or((mant, ""));
+    let (fixed, rep) = match frac.split_once('(') {
+        Some((fixed, rep)) => (fixed, rep.strip_suffix(')').filter( |r| !r.is_empty() ).ok_or_else( bad )?),
+        None => (frac, ""),
+    };
+    if int.is_empty() && fixed.is_empty() && rep.is_empty() {
+        return Err( bad() );
+    }
+    if ![int, fixed, rep].iter().all( |d| d.bytes().all( |c| c.is_ascii_digit() ) ) {
+        return Err( bad() );
+    }
+    if exp.unsigned_abs() > MAX_EXP as u32 {
+        return Err( FracError::Exponent(exp) );
+    }
+    let digits = |d: &str| if d.is_empty() { Integer::new() } else { d.parse::<Integer>().unwrap() };
+    // int.fixed(rep) = (int fixed + rep/(10^r - 1)) / 10^k
+    let mut num = digits( &format!("{}{}", int, fixed) );
+    let mut den = Integer::from(10).pow( fixed.len() as u32 );
+    if !rep.is_empty() {
+        let nines = Integer::from(10).pow( rep.len() as u32 ) - 1u32;
+        num = num * &nines + digits(rep);
+        den *= nines;
+    }
+    if exp >= 0 {
+        num *= Integer::from(10).pow( exp as u32 );
+    } else {
+        den *= Integer::from(10).pow( exp.unsigned_abs() );
+    }
+    if neg {
+        num = -num;
+    }
+    let mut z = Frac::from_int(num, den);
+    z.normalise();
+    Ok(z)
+}
+
+impl FromStr for Frac {
+    type Err = FracError;
+    fn from_str( s: &str ) -> Result<Self, FracError> {
+        // "355/113", "3.14159", "1.5e-7", "0.(142857)" or quotients of such, like "1.5/2e3"
+        let t = s.trim();
+        let z = match t.split_once('/') {
+            Some((a, b)) => parse_decimal( a.trim() ).and_then( |a| Ok( a / parse_decimal( b.trim() )? ) ),
+            None => parse_decimal( t ),
+        };
+        // unrecognised as a whole
+        z.map_err( |e| match e {
+            FracError::Parse(_) => FracError::Parse(s.to_string()),
+            e => e,
+        })
+    }
+}
+
 impl Display for Frac {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         let mut num_str = self.num.to_string();
@@ -74,13 +443,93 @@ impl Display for Frac {
 }
 
 type PropF = fn(i128) -> (i128, i128);
+type PropG = dyn Fn(i128) -> (Integer, Integer) + Send + Sync;
+type SetF = dyn Fn(i128) -> Option<(Frac, Frac)> + Send + Sync;
 type Mat<T> = ((T, T), (T, T));
 
+#[derive(Clone)]
+pub enum Gen {
+    // The terms (a_n, b_n) of b_0 + a_1/(b_1 + a_2/(b_2 + ...)), a_0 being unused
+    Ab(PropF),      // plain functions, like the *_ab of math_consts
+    Dyn(Arc<PropG>),// closures, e.g. transformations of other generators
+}
+
+impl Gen {
+    pub fn from( ab: PropF ) -> Self {
+        Gen::Ab(ab)
+    }
+    pub fn new( f: impl Fn(i128) -> (Integer, Integer) + Send + Sync + 'static ) -> Self {
+        Gen::Dyn( Arc::new(f) )
+    }
+    pub fn ab( &self, n: i128 ) -> (Integer, Integer) {
+        match self {
+            Gen::Ab(f) => {
+                let (a, b) = f(n);
+                (Integer::from(a), Integer::from(b))
+            },
+            Gen::Dyn(f) => f(n),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub enum Tail {
+    // What is known of the tail t_n = a_{n+1}/(b_{n+1} + a_{n+2}/(...)), whose value x = M(t_n) is
+    // given by the matrix M = [frac2 frac1] of the two last convergents (kept by all reductions)
+    Convergents,    // t_n in [0, inf], i.e. the value lies between frac1 and frac2 (the default)
+    Positive,       // all further a_n, b_n > 0, so t_n in [0, a_{n+1}/b_{n+1}]
+    Sets(Arc<SetF>),// value sets V_n containing t_n, e.g. [-n^3/17, 0] for zeta3_ab with n >= 1,
+                    // None where nothing is known (then as Convergents)
+}
+
+impl Tail {
+    pub fn sets( v: impl Fn(i128) -> Option<(Frac, Frac)> + Send + Sync + 'static ) -> Self {
+        Tail::Sets( Arc::new(v) )
+    }
+
+    pub fn check( &self, ab: &Gen, from: i128, to: i128 ) -> bool {
+        // Whether a_k/(b_k + V_k) lies within V_{k-1} for k in from+1..=to, the condition making the
+        // value sets rigorous (if it holds for all k), skipping the k without both sets. Only a finite
+        // prefix can be checked here.
+        match self {
+            Tail::Convergents | Tail::Positive => ((from + 1)..=to).all( |k| {
+                let (a, b) = ab.ab(k);
+                a > 0 && b > 0
+            }),
+            Tail::Sets(v) => ((from + 1)..=to).all( |k| {
+                let (a, b) = ab.ab(k);
+                let ( Some((lo, hi)), Some((l, u)) ) = ( v(k - 1), v(k) ) else {
+                    return true;
+                };
+                let step = |w: &Frac| -> Option<Frac> {
+                    let mut w = w.clone();
+                    w.normalise();
+                    let d = Integer::from( &b * &w.den ) + &w.num;
+                    if d == 0 {
+                        return None;
+                    }
+                    Some( Frac::from_int( Integer::from( &a * &w.den ), d ) )
+                };
+                match ( step(&l), step(&u) ) {
+                    (Some(x), Some(y)) => {
+                        (x.den < 0) == (y.den < 0) && lo <= x && x <= hi && lo <= y && y <= hi
+                    },
+                    _ => false,
+                }
+            }),
+        }
+    }
+}
+
+#[derive(Clone)]
 pub struct KFrac {
     pub n: i128,
-    ab: PropF,
+    ab: Gen,
     pub frac1: Frac,
     pub frac2: Frac,
+    pub tail: Tail,
+    pub observer: Watch, // for prop_bs
+    pub name: Option<&'static str>, // of the math_consts generator, what serialisation stores
 }
 
 impl Display for KFrac {
@@ -96,38 +545,135 @@ impl Display for KFrac {
 
 impl KFrac {
     pub fn from( ab: PropF ) -> Self {
+        KFrac::from_gen( Gen::Ab(ab) )
+    }
+    pub fn from_gen( ab: Gen ) -> Self {
+        let b0 = ab.ab(0).1;
         KFrac {
             n: 0,
-            ab: ab,
+            ab,
             // New fractions are always: | 1  b_0 |
             //                           | 0  1   |
             frac1: Frac::from_i128( 1, 0),
-            frac2: Frac::from_i128( ab(0).1 , 1),
+            frac2: Frac::from_int( b0, Integer::from(1) ),
+            tail: Tail::Convergents,
+            observer: None,
+            name: None,
         }
     }
+    pub fn by_name( name: &str ) -> Option<Self> {
+        // one of math_consts ("pi", "e", "zeta3", ...)
+        let name = math_consts::NAMES.into_iter().find( |&c| c == name )?;
+        let mut k = KFrac::from( math_consts::by_name(name)? );
+        k.name = Some(name);
+        Some(k)
+    }
+    pub fn with_tail( mut self, tail: Tail ) -> Self {
+        self.tail = tail;
+        self
+    }
+    pub fn with_observer( mut self, o: Arc<dyn Observer> ) -> Self {
+        self.observer = Some(o);
+        self
+    }
+    pub fn gen( &self ) -> &Gen {
+        &self.ab
+    }
+
+    pub fn image( &self, w: &Frac ) -> Frac {
+        // M(w) = (A_n + w A_{n-1})/(B_n + w B_{n-1}), the value for the tail w (frac2 at 0, frac1 at inf)
+        let num = Integer::from( &self.frac2.num * &w.den ) + &w.num * &self.frac1.num;
+        let den = Integer::from( &self.frac2.den * &w.den ) + &w.num * &self.frac1.den;
+        Frac::from_int( num, den )
+    }
+    pub fn bounds( &self ) -> Option<(Frac, Frac)> {
+        // The image M(V_n) of the value set of the tail, None when that is just [frac1, frac2] (for
+        // Tail::Convergents, or no set given). If the pole of M falls into V_n the image is unbounded, reported as
+        // [1/0, -1/0] so that all reductions wait for more convergents.
+        let (mut l, mut u) = match &self.tail {
+            Tail::Convergents => return None,
+            Tail::Positive => {
+                let (a, b) = self.ab.ab(self.n + 1);
+                ( Frac::from(0), Frac::from_int(a, b) )
+            },
+            Tail::Sets(v) => v(self.n)?,
+        };
+        l.normalise();
+        u.normalise();
+        let d1 = Integer::from( &self.frac2.den * &l.den ) + &l.num * &self.frac1.den;
+        let d2 = Integer::from( &self.frac2.den * &u.den ) + &u.num * &self.frac1.den;
+        if d1 == 0 || d2 == 0 || (d1 < 0) != (d2 < 0) {
+            return Some(( Frac::from_i128(1, 0), Frac::from_i128(-1, 0) ));
+        }
+        Some(( self.image(&l), self.image(&u) ))
+    }
+    pub fn enclosure( &self ) -> (Cow<'_, Frac>, Cow<'_, Frac>) {
+        // The two ends of the interval known to contain the value, as used by all reductions
+        match self.bounds() {
+            Some((l, u)) => ( Cow::Owned(l), Cow::Owned(u) ),
+            None => ( Cow::Borrowed(&self.frac1), Cow::Borrowed(&self.frac2) ),
+        }
+    }
+    pub fn tail_estimate( &self ) -> Frac {
+        // Fixed point of w = a/(b + w) for the next terms, i.e. the tail if they stayed constant:
+        // w = 2a/(b + sgn(b) sqrt(b^2 + 4a)), the root closest to a/b. Not a bound, just a good guess.
+        let (a, b) = self.ab.ab(self.n + 1);
+        let d = Integer::from( &b * &b ) + Integer::from( &a << 2u32 );
+        if b == 0 || d < 0 {
+            return Frac::from_int( a, b );
+        }
+        // sqrt(d) to 64 more bits, in fixed point
+        let mut s = (d << 128u32).sqrt_of();
+        if b < 0 {
+            s = -s;
+        }
+        let mut w = Frac::from_int( a << 65u32, (b << 64u32) + s );
+        w.normalise();
+        w
+    }
+    pub fn modified( &self ) -> Frac {
+        // The modified approximant M(w_n) with the fixed point tail estimate
+        self.image( &self.tail_estimate() )
+    }
+
     pub fn atleast1(&self) -> (bool, bool) {
-        (self.frac1.atleast1() , self.frac2.atleast1() )
+        let (e1, e2) = self.enclosure();
+        (e1.atleast1() , e2.atleast1() )
     }
     pub fn atleast2(&self) -> (bool, bool) {
-        (self.frac1.atleast2() , self.frac2.atleast2() )
+        let (e1, e2) = self.enclosure();
+        (e1.atleast2() , e2.atleast2() )
     }
     pub fn finite(&self) -> bool {
     // Only when both are 1/0 can we be sure it's infinite
-        self.frac1.finite() || self.frac2.finite()
+        let (e1, e2) = self.enclosure();
+        e1.finite() || e2.finite()
     }
     pub fn gcd_div(&mut self) {
         // divide both convergents by gcd of all 4 components
-        let g1 = self.frac1.num.gcd_ref( &self.frac1.den ).complete();
-        let mut g = self.frac2.num.gcd_ref( &self.frac2.den ).complete();
-        g.gcd_mut( &g1 );
+        let g1 = self.frac1.num.gcd_of( &self.frac1.den );
+        let g = self.frac2.num.gcd_of( &self.frac2.den ).gcd_of( &g1 );
         self.frac1.num /= &g;
         self.frac1.den /= &g;
         self.frac2.num /= &g;
         self.frac2.den /= &g;
     }
+    pub fn pow2_div(&mut self) {
+        // the common powers of two of all 4 components
+        let z = [&self.frac1.num, &self.frac1.den, &self.frac2.num, &self.frac2.den].iter()
+            .filter_map( |c| c.find_one(0) ).min().unwrap_or(0);
+        self.frac1.num >>= z;
+        self.frac1.den >>= z;
+        self.frac2.num >>= z;
+        self.frac2.den >>= z;
+    }
+    pub fn bits(&self) -> u64 {
+        self.frac1.bits() + self.frac2.bits()
+    }
 
     pub fn prop_bs(&mut self, new: i128) {
         // binary splitting propagation, to advance 'self' by 'new' convergents
+        let start = Instant::now();
         fn mat_mul( ((a11, a12), (a21, a22)): Mat<Integer>,
                     ((b11, b12), (b21, b22)): Mat<Integer> ) -> Mat<Integer> {
             let s11 = a11.clone() * &b11 + a12.clone() * &b21;
@@ -137,33 +683,58 @@ impl KFrac {
            ( (s11, s12), (s21, s22) ) 
         }
 
-        fn matrix_bs(f: PropF, m1: i128, m2: i128) -> Mat<Integer> {
+        fn matrix_bs(f: &Gen, m1: i128, m2: i128) -> Mat<Integer> {
             if m1 == m2 {
-                let (a,b) = f(m1);
-                ( (Integer::from(0), Integer::from(a)), 
-                  (Integer::from(1), Integer::from(b)) )
+                let (a,b) = f.ab(m1);
+                ( (Integer::from(0), a), 
+                  (Integer::from(1), b) )
             } else {
                 let mid = m1 + (m2-m1)/2;
                 mat_mul(matrix_bs(f, m1, mid), matrix_bs(f, mid + 1, m2))
             }
         }
 
+        fn matrix_obs(f: &Gen, m1: i128, m2: i128, grain: i128, tick: &dyn Fn(i128, u64)) -> Mat<Integer> {
+            // the same splitting, reporting each finished block of up to 'grain' terms
+            if m2 - m1 < grain {
+                let m = matrix_bs(f, m1, m2);
+                let ((a11, a12), (a21, a22)) = &m;
+                tick(m2, [a11, a12, a21, a22].iter().map( |a| a.significant_bits() as u64 ).sum());
+                m
+            } else {
+                let mid = m1 + (m2-m1)/2;
+                mat_mul(matrix_obs(f, m1, mid, grain, tick), matrix_obs(f, mid + 1, m2, grain, tick))
+            }
+        }
+
+        let m = match &self.observer {
+            None => matrix_bs(&self.ab, self.n + 1, self.n + new),
+            Some(_) => {
+                let n0 = self.n;
+                let tick = |m: i128, bits: u64| {
+                    report( &self.observer, start, Stage::Split, (m - n0) as usize, m, bits );
+                };
+                matrix_obs(&self.ab, self.n + 1, self.n + new, (new/16).max(1), &tick)
+            },
+        };
         ((self.frac1.num, self.frac2.num), 
          (self.frac1.den, self.frac2.den)) = mat_mul( 
             ((self.frac1.num.clone(), self.frac2.num.clone()), 
              (self.frac1.den.clone(), self.frac2.den.clone())), 
-            matrix_bs(self.ab, self.n + 1, self.n + new) );
+            m );
 
         //self.frac1.num = a11;
         //self.frac2.num = a12;
         //self.frac1.den = a21;
         //self.frac2.den = a22;
         self.n += new;
+        report( &self.observer, start, Stage::Done, new as usize, self.n, self.bits() );
     }
 
     pub fn prop(&mut self) {
+        // the generator first, so that one unwinding (a failed Python callback) leaves all unchanged
+        let (a, b) = self.ab.ab(self.n + 1);
         self.n += 1;
-        let (a, b) = (self.ab)(self.n);
         self.frac1.num *= &a;
         self.frac1.den *= &a;
         self.frac1.num += &b * &self.frac2.num;
@@ -171,6 +742,49 @@ impl KFrac {
         std::mem::swap( &mut self.frac1, &mut self.frac2 );
     }
 
+    #[cfg(feature = "gmp")]
+    pub fn evaluate(&mut self, bits: u32, budget: &Budget) -> Result<(Float, Float), RedError> {
+        // The value correctly rounded to 'bits' of precision, with a certified error radius.
+        // Propagates (by binary splitting) until [frac1, frac2] is narrower than 2^-bits and both ends
+        // round to the same Float. The ends come from enclosure, so with Tail::Convergents this assumes
+        // the two last convergents bracket the value. A value on a rounding boundary never gets
+        // there, so it is the budget that stops it.
+        if bits == 0 || bits > rug::float::prec_max() - 32 {
+            return Err( RedError::Precision(bits) );
+        }
+        let meter = Meter::new( budget, self.n );
+        let mut step = 16;
+        loop {
+            let (e1, e2) = self.enclosure();
+            if e1.finite() && e2.finite() {
+                let err_num = (Integer::from(&e2.num * &e1.den) - &e1.num * &e2.den).abs() << bits;
+                let err_den = Integer::from(&e2.den * &e1.den).abs();
+                if err_num < err_den {
+                    let (lo, hi) = if e1 <= e2 { (&e1, &e2) } else { (&e2, &e1) };
+                    let lo = Rational::from( (lo.num.clone(), lo.den.clone()) );
+                    let hi = Rational::from( (hi.num.clone(), hi.den.clone()) );
+                    let value = Float::with_val(bits, &lo);
+                    if value == Float::with_val(bits, &hi) {
+                        // endpoints rounded outward, the radius upward
+                        let lo_down = Float::with_val_round(bits + 32, &lo, Round::Down).0;
+                        let hi_up = Float::with_val_round(bits + 32, &hi, Round::Up).0;
+                        let r1 = Float::with_val_round(32, &value - &lo_down, Round::Up).0;
+                        let r2 = Float::with_val_round(32, &hi_up - &value, Round::Up).0;
+                        return Ok( (value, r1.max(&r2)) );
+                    }
+                }
+            }
+            meter.check( self.n, self.bits() )?;
+            // not past the convergent limit in one go
+            if let Some(m) = budget.convergents {
+                step = step.min( (m - self.n).max(1) );
+            }
+            self.prop_bs(step);
+            step *= 2;
+        }
+    }
+
+    #[cfg(feature = "gmp")]
     pub fn stats(&self) -> (u32, f64) {
         // Some rudimentary error and precision estimates
         let den_len = &self.frac2.den.to_string_radix(10).len();
@@ -193,16 +807,30 @@ impl KFrac {
     }
 }
 
-pub trait RegFracOps {}
+pub trait RegFracOps {
+    // The operation of a term t: x -> 1/(x - t)
+    fn term( &mut self, t: &Integer );
+    // Whether the expansion has ended, i.e. the remainder is known to be 1/0
+    fn finished( &self ) -> bool {
+        false
+    }
+}
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RegFrac<T: RegFracOps> {
     pub tape: Vec<u128>,
     pub remainder: T,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub norm: Normaliser,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub observer: Watch,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub sink: Option<Sink>,
 }
 
 impl<T: RegFracOps> From<T> for RegFrac<T> {
     fn from( a: T ) -> Self {
-        RegFrac { tape: Vec::new(), remainder: a }
+        RegFrac { tape: Vec::new(), remainder: a, norm: Normaliser::default(), observer: None, sink: None }
     }
 }
 
@@ -213,12 +841,231 @@ where T: RegFracOps + Display {
     }
 }
 
-impl RegFracOps for Frac {}
+impl<T: RegFracOps> RegFrac<T> {
+    pub fn with_observer( mut self, o: Arc<dyn Observer> ) -> Self {
+        self.observer = Some(o);
+        self
+    }
+    pub fn with_sink( mut self, s: Sink ) -> Self {
+        self.sink = Some(s);
+        self
+    }
+    pub fn with_norm( mut self, policy: Norm ) -> Self {
+        self.norm.policy = policy;
+        self
+    }
+    pub fn len( &self ) -> usize {
+        // terms so far, with those already streamed out of the tape window
+        self.tape.len() + self.sink.as_ref().map_or( 0, |s| s.dropped() )
+    }
+    pub fn is_empty( &self ) -> bool {
+        self.len() == 0
+    }
+    pub fn trimmed( &self ) -> bool {
+        // whether the sink dropped terms from the front of the tape, as for KLog
+        self.len() != self.tape.len()
+    }
+    pub(crate) fn stream( &mut self ) -> Result<(), RedError> {
+        match self.sink.as_mut() {
+            Some(s) => s.terms( &mut self.tape ).map_err( |e| RedError::Io( e.kind() ) ),
+            None => Ok(()),
+        }
+    }
+    pub fn close_sink( &mut self ) -> std::io::Result<()> {
+        // streams what's left and closes the sink (which stays attached, so len() keeps counting)
+        if let Some(s) = self.sink.as_mut() {
+            s.terms( &mut self.tape )?;
+            if self.remainder.finished() { s.finish()? } else { s.close()? };
+        }
+        Ok(())
+    }
+
+    // Approximations from the terms in the tape. Errors are measured against the value of a finished
+    // expansion, otherwise against the tape interval, with the largest error over it reported; where
+    // the interval does not decide between two candidates the results stop. Nothing is given once
+    // the sink has trimmed the tape.
+
+    pub fn convergents( &self ) -> Vec<Frac> {
+        if self.trimmed() {
+            return Vec::new();
+        }
+        let mut convs = Vec::with_capacity( self.tape.len() );
+        let (mut p0, mut q0) = (Integer::from(0), Integer::from(1));
+        let (mut p1, mut q1) = (Integer::from(1), Integer::from(0));
+        for &a in self.tape.iter() {
+            p0 += Integer::from(a) * &p1;
+            q0 += Integer::from(a) * &q1;
+            std::mem::swap( &mut p0, &mut p1 );
+            std::mem::swap( &mut q0, &mut q1 );
+            convs.push( Frac::from_int(p1.clone(), q1.clone()) );
+        }
+        convs
+    }
+
+    pub fn interval( &self ) -> Option<(Frac, Frac)> {
+        // Where the number lies if more terms follow: between p_n/q_n and (p_n + p_{n-1})/(q_n + q_{n-1}),
+        // for the rest of the expansion between 0 and 1. None for an empty tape, and for a trimmed one
+        // unless the sink keeps the map of the dropped terms.
+        if self.is_empty() {
+            return None;
+        }
+        let mut m = stream::mobius( &self.tape, stream::term_map );
+        if self.trimmed() {
+            let d = self.sink.as_ref()?.map()?;
+            m = stream::compose( d, &m );
+        }
+        let [p1, p0, q1, q0] = m;
+        Some(( Frac::from_int( p1.clone(), q1.clone() ), Frac::from_int( p1 + p0, q1 + q0 ) ))
+    }
+
+    pub(crate) fn ends( &self ) -> Option<(Frac, Frac)> {
+        // The values the number can take, in increasing order: the last convergent of a finished
+        // expansion (the first end of the interval), the interval otherwise
+        let (a, b) = self.interval()?;
+        if self.remainder.finished() {
+            return Some( (a.clone(), a) );
+        }
+        Some( if a <= b { (a, b) } else { (b, a) } )
+    }
+
+    fn bound( ends: &(Frac, Frac), y: &Frac ) -> Frac {
+        // the largest error of y over the ends, which is over all between them as y is not inside
+        Self::error( &ends.0, y ).max( Self::error( &ends.1, y ) )
+    }
+
+    fn error( x: &Frac, y: &Frac ) -> Frac {
+        // |x - y| for non-negative x, y with positive denominators
+        let mut e = Frac::from_int(
+            (Integer::from( &x.num * &y.den ) - &y.num * &x.den).abs(),
+            Integer::from( &x.den * &y.den ) );
+        e.gcd_div();
+        e
+    }
+
+    fn semis( &self, half: bool ) -> Vec<Frac> {
+        // Intermediate fractions (p_{k-1} + m p_k)/(q_{k-1} + m q_k) for 1 <= m <= a_{k+1}, with
+        // increasing denominators, the m = a_{k+1} ones being the convergents. For best approximations
+        // only m >= a_{k+1}/2 can qualify, which is what 'half' restricts to.
+        let mut semis = Vec::new();
+        let Some(&a0) = self.tape.first().filter( |_| !self.trimmed() ) else {
+            return semis;
+        };
+        let (mut p0, mut q0) = (Integer::from(1), Integer::from(0));
+        let (mut p1, mut q1) = (Integer::from(a0), Integer::from(1));
+        semis.push( Frac::from_int(p1.clone(), q1.clone()) );
+        for &a in self.tape[1..].iter() {
+            let m0 = if half { a/2 + a%2 } else { 1 }.max(1);
+            let mut p = Integer::from(m0 - 1) * &p1 + &p0;
+            let mut q = Integer::from(m0 - 1) * &q1 + &q0;
+            for _ in m0..=a {
+                p += &p1;
+                q += &q1;
+                semis.push( Frac::from_int(p.clone(), q.clone()) );
+            }
+            (p0, q0) = (p1, q1);
+            (p1, q1) = (p, q);
+        }
+        semis
+    }
+
+    pub fn semiconvergents( &self ) -> Vec<Frac> {
+        self.semis( false )
+    }
+
+    pub fn best_first_kind( &self ) -> Vec<(Frac, Frac)> {
+        // Best approximations of the first kind, |x - p/q| < |x - p'/q'| for all q' < q, with errors
+        self.best( |x, s| Self::error( x, s ) )
+    }
+
+    pub fn best_second_kind( &self ) -> Vec<(Frac, Frac)> {
+        // Best approximations of the second kind, |qx - p| < |q'x - p'| for all q' < q, with errors
+        // |x - p/q| as above. These are the convergents, save for a possible first one.
+        self.best( |x, s| {
+            let mut e = Self::error( x, s );
+            e.num *= &s.den;
+            e
+        })
+    }
+
+    fn best( &self, measure: impl Fn(&Frac, &Frac) -> Frac ) -> Vec<(Frac, Frac)> {
+        // No candidate lies inside the interval, so the measures are linear on it, and a comparison
+        // holding at both ends holds for the number
+        let Some(ends) = self.ends() else {
+            return Vec::new();
+        };
+        let mut best: Vec<(Frac, [Frac; 2])> = Vec::new();
+        for s in self.semis( true ) {
+            let d = [ measure( &ends.0, &s ), measure( &ends.1, &s ) ];
+            if let Some((b, db)) = best.last() {
+                let better = ( d[0] < db[0], d[1] < db[1] );
+                if better.0 != better.1 {
+                    break;
+                }
+                if !better.0 {
+                    continue;
+                }
+                if b.den == s.den {
+                    best.pop();
+                }
+            }
+            best.push( (s, d) );
+        }
+        best.into_iter().map( |(s, _)| { let e = Self::bound( &ends, &s ); (s, e) } ).collect()
+    }
+
+    pub fn best_approx( &self, max_den: &Integer ) -> Option<(Frac, Frac)> {
+        // The best approximation with denominator at most max_den, with its error: the last convergent
+        // p_k/q_k within the bound, or the largest admissible intermediate fraction if that is closer.
+        // None when the tape does not tell, i.e. max_den reaches past it or the interval straddles
+        // the midpoint of the two.
+        let ends = self.ends()?;
+        if *max_den < 1 {
+            return None;
+        }
+        let (mut p0, mut q0) = (Integer::from(0), Integer::from(1));
+        let (mut p1, mut q1) = (Integer::from(1), Integer::from(0));
+        for &a in self.tape.iter() {
+            let q2 = Integer::from(a) * &q1 + &q0;
+            if q2 > *max_den {
+                let m = Integer::from( max_den - &q0 ) / &q1;
+                let semi = Frac::from_int( m.clone() * &p1 + &p0, m * &q1 + &q0 );
+                let conv = Frac::from_int( p1, q1 );
+                let closer = |x: &Frac| Self::error( x, &semi ) < Self::error( x, &conv );
+                let c = closer( &ends.0 );
+                if c != closer( &ends.1 ) {
+                    return None;
+                }
+                let s = if c { semi } else { conv };
+                let e = Self::bound( &ends, &s );
+                return Some( (s, e) );
+            }
+            p0 += Integer::from(a) * &p1;
+            std::mem::swap( &mut p0, &mut p1 );
+            q0 = q2;
+            std::mem::swap( &mut q0, &mut q1 );
+        }
+        if ends.0 != ends.1 {
+            return None;
+        }
+        let e = Self::error( &ends.0, &ends.1 );
+        Some( (ends.0, e) )
+    }
+}
+
+impl RegFracOps for Frac {
+    fn term( &mut self, t: &Integer ) {
+        self.num -= t * &self.den;
+        self.inv();
+    }
+    fn finished( &self ) -> bool {
+        !self.finite()
+    }
+}
 
 impl RegFrac<Frac> {
     pub fn red1(&mut self) {
         let rem = &mut self.remainder;
-        let (a, b) = rem.num.div_rem_ref(&rem.den).complete();
+        let (a, b) = rem.num.div_rem_of(&rem.den);
         if let Some(c) = a.to_u128() {
             self.tape.push(c);
             rem.num = b;
@@ -233,27 +1080,71 @@ impl RegFrac<Frac> {
         if verbose {
             while self.remainder.finite() {
                 self.red1();
+                self.norm.step( &mut self.remainder );
                 println!("{}", self);
+                if let Err(e) = self.stream() {
+                    println!("\u{274c} RegFrac<Frac>: {}", e);
+                    return;
+                }
             }
         } else {
             while self.remainder.finite() {
                 self.red1();
+                self.norm.step( &mut self.remainder );
+                if let Err(e) = self.stream() {
+                    println!("\u{274c} RegFrac<Frac>: {}", e);
+                    return;
+                }
             }
         }
+        if let Err(e) = self.close_sink() {
+            println!("\u{274c} RegFrac<Frac>: {}", e);
+        }
     }
 }
 
-impl RegFracOps for KFrac {}
+impl RegFracOps for KFrac {
+    fn term( &mut self, t: &Integer ) {
+        for f in [&mut self.frac1, &mut self.frac2] {
+            f.term( t );
+        }
+    }
+}
 
 impl RegFrac<KFrac> {
     pub fn red1(&mut self, ratio: usize) -> bool {
         let rem = &mut self.remainder;
+        if let Some((e1, e2)) = rem.bounds() {
+            // with a known tail the term is read off the enclosure, then applied to both convergents
+            if !e1.finite() || !e2.finite() {
+                rem.prop();
+                return false;
+            }
+            let a1 = Integer::from( &e1.num / &e1.den );
+            if a1 == Integer::from( &e2.num / &e2.den ) {
+                if let Some(c) = a1.to_u128() {
+                    self.tape.push(c);
+                    for f in [&mut rem.frac1, &mut rem.frac2] {
+                        f.num -= &a1 * &f.den;
+                        std::mem::swap( &mut f.num, &mut f.den);
+                    }
+                    return true;
+                } else {
+                    println!("The next term does not fit in u128.");
+                    panic!();
+                }
+            }
+            for _ in 0..ratio {
+                rem.prop();
+            }
+            return false;
+        }
         if !rem.frac1.finite() || !rem.frac2.finite() {
             rem.prop();
             return false;
         }
-        let (a1, b1) = rem.frac1.num.div_rem_ref(&rem.frac1.den).complete();
-        let (a2, b2) = rem.frac2.num.div_rem_ref(&rem.frac2.den).complete();
+        let (a1, b1) = rem.frac1.num.div_rem_of(&rem.frac1.den);
+        let (a2, b2) = rem.frac2.num.div_rem_of(&rem.frac2.den);
         if a1 == a2 {
             if let Some(c) = a1.to_u128() {
                 self.tape.push(c);
@@ -261,7 +1152,7 @@ impl RegFrac<KFrac> {
                 std::mem::swap( &mut rem.frac1.num, &mut rem.frac1.den);
                 rem.frac2.num = b2;
                 std::mem::swap( &mut rem.frac2.num, &mut rem.frac2.den);
-                return true;
+                true
             } else {
                 println!("The next term does not fit in u128.");
                 panic!();
@@ -270,58 +1161,313 @@ impl RegFrac<KFrac> {
             for _ in 0..ratio {
                 rem.prop();
             }
-            return false;
+            false
         }
     }
 
     pub fn red_adapt(&mut self, new_terms: usize, chunk: usize) {
+        if let Err(e) = self.try_red_adapt( new_terms, chunk, &Budget::new() ) {
+            println!("\u{274c} RegFrac<KFrac>: {}", e);
+        }
+    }
+    fn adapt_ratio( g: f32, dn: i128, dl: usize ) -> f32 {
+        // convergents per term over the last chunk; one that gave no terms at least doubles the ratio
+        if dl > 0 { dn as f32 / dl as f32 } else { 2.0 * g.max( dn as f32 ) }
+    }
+    pub fn try_red_adapt(&mut self, new_terms: usize, chunk: usize, budget: &Budget) -> Result<(), RedError> {
         if new_terms <= chunk { // 10_000 seems like a good choice
-            self.red( new_terms, None )
+            self.try_red( new_terms, None, budget )
         } else {
-            let mut l0 = self.tape.len();
+            let mut meter = Meter::new( budget, self.remainder.n );
+            let start = Instant::now();
+            let mut l0 = self.len();
             let limit = l0 + new_terms;
             let mut n0 = self.remainder.n;
             for _ in 0..chunk {
-                self.remainder.prop()
+                self.remainder.prop();
+                meter.progress( self.remainder.n ); // planned, not a stall
+                meter.check( self.remainder.n, self.remainder.bits() )?;
             }
-            self.remainder.gcd_div(); // Move it Afterwards???
-            while self.red1(0) {} // Extract as many as possible without prop
-            let mut l1 = self.tape.len();
-            let mut g = (self.remainder.n - n0) as f32 / (l1 - l0 ) as f32;
-            println!("{}", g);
+            self.norm.chunk( &mut self.remainder ); // Move it Afterwards???
+            report( &self.observer, start, Stage::Prop, l0, self.remainder.n, self.remainder.bits() );
+            while self.red_lehmer( usize::MAX ) > 0 || self.red1(0) {} // Extract as many as possible without prop
+            self.stream()?;
+            let mut l1 = self.len();
+            let mut g = Self::adapt_ratio( 1.0, self.remainder.n - n0, l1 - l0 );
+            let cap = chunk.saturating_mul( 64 ) as f32; // propagation per round, for ratios gone wild
             while limit > l1 {
                 n0 = self.remainder.n;
                 l0 = l1;
-                let loc_limit = (limit - l1).min(chunk) as f32 * g;
+                let loc_limit = ((limit - l1).min(chunk) as f32 * g).min( cap );
                 for _ in 0..(loc_limit as usize) {
                     self.remainder.prop();
+                    meter.progress( self.remainder.n );
+                    meter.check( self.remainder.n, self.remainder.bits() )?;
                 }
-                self.remainder.gcd_div();
-                let g0 = (g as usize).max(1);
-                for _ in 0..(limit-l1) {
-                    if !self.red1( g0 ) {
+                self.norm.chunk( &mut self.remainder );
+                report( &self.observer, start, Stage::Prop, l1, self.remainder.n, self.remainder.bits() );
+                let g0 = (g as usize).clamp( 1, chunk.max(1) );
+                while self.len() < limit {
+                    if self.red_lehmer( limit - self.len() ) == 0 && !self.red1( g0 ) {
                         break;
                     }
+                    meter.progress( self.remainder.n );
+                    self.norm.step( &mut self.remainder );
+                    self.stream()?;
+                    report( &self.observer, start, Stage::Red, self.len(), self.remainder.n, self.remainder.bits() );
                 }
-                l1 = self.tape.len();
-                g = (self.remainder.n - n0) as f32 / (l1-l0) as f32;
-                println!("tape len {} converget #{} ratio {}", l1, self.remainder.n, g);
+                l1 = self.len();
+                meter.check( self.remainder.n, self.remainder.bits() )?;
+                g = Self::adapt_ratio( g, self.remainder.n - n0, l1 - l0 );
             }
+            report( &self.observer, start, Stage::Done, self.len(), self.remainder.n, self.remainder.bits() );
+            Ok(())
         }
     }
     pub fn red(&mut self, new_terms: usize, ratio: Option<f32> ) {
-        let limit = self.tape.len() + new_terms;
+        if let Err(e) = self.try_red( new_terms, ratio, &Budget::new() ) {
+            println!("\u{274c} RegFrac<KFrac>: {}", e);
+        }
+    }
+    pub fn try_red(&mut self, new_terms: usize, ratio: Option<f32>, budget: &Budget ) -> Result<(), RedError> {
+        // As red, but gives up (keeping all the terms so far) when the budget runs out
+        let mut meter = Meter::new( budget, self.remainder.n );
+        let start = Instant::now();
+        let limit = self.len() + new_terms;
         // assuming 1 KFrac step produces ~1 regular fraction term is a reasonable default
         let r0 = ratio.unwrap_or(1.0);
         let scaled = (r0 * new_terms as f32).ceil() as usize;
         for _ in 0..scaled {
             self.remainder.prop();
+            meter.progress( self.remainder.n ); // planned, not a stall
+            meter.check( self.remainder.n, self.remainder.bits() )?;
         }
-        self.remainder.gcd_div();
+        self.norm.chunk( &mut self.remainder );
+        report( &self.observer, start, Stage::Prop, self.len(), self.remainder.n, self.remainder.bits() );
         // prop is repeated rx times inside red1
         let rx  = r0.ceil().max(1.0) as usize;
-        while self.tape.len() < limit {
-            self.red1(rx);
+        while self.len() < limit {
+            if self.red_lehmer( limit - self.len() ) > 0 || self.red1(rx) {
+                meter.progress( self.remainder.n );
+            }
+            self.norm.step( &mut self.remainder );
+            self.stream()?;
+            report( &self.observer, start, Stage::Red, self.len(), self.remainder.n, self.remainder.bits() );
+            meter.check( self.remainder.n, self.remainder.bits() )?;
+        }
+        report( &self.observer, start, Stage::Done, self.len(), self.remainder.n, self.remainder.bits() );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn f( a: i128, b: i128 ) -> Frac {
+        Frac::from_i128( a, b )
+    }
+
+    fn int( a: i128 ) -> Option<Integer> {
+        Some( Integer::from(a) )
+    }
+
+    #[test]
+    fn order_with_mixed_signs() {
+        assert_eq!( f(1, -2), f(-1, 2) );
+        assert_eq!( f(-1, -2), f(1, 2) );
+        assert_eq!( f(2, 4), f(-1, -2) );
+        assert!( f(1, -2) < f(1, 3) );
+        assert!( f(-1, -3) < f(1, 2) );
+        assert!( f(3, -2) < f(-1, 3) );
+        let mut xs = vec![ f(1, 0), f(-1, -2), f(0, 0), f(3, -2), f(2, 1), f(0, -5), f(-1, 0), f(-1, 3) ];
+        xs.sort();
+        assert_eq!( xs, [ f(0, 0), f(-1, 0), f(-3, 2), f(-1, 3), f(0, 1), f(1, 2), f(2, 1), f(1, 0) ] );
+    }
+
+    #[test]
+    fn order_of_infinities_and_nan() {
+        let big = f(i128::MAX, 1);
+        assert!( f(1, 0) > big && f(7, 0) > big );
+        assert!( f(-1, 0) < -&big );
+        assert_eq!( f(1, 0), f(7, 0) );
+        assert!( f(-1, 0) < f(1, 0) );
+        // 0/0 is below everything, and equal only to itself
+        assert_eq!( f(0, 0), f(0, 0) );
+        assert!( f(0, 0) < f(-1, 0) );
+        assert!( f(0, 0) < f(0, 1) );
+        assert_eq!( f(0, 0).max( f(-1, 0) ), f(-1, 0) );
+    }
+
+    #[test]
+    fn ops_with_negative_denominators() {
+        let z = f(1, -2) + f(1, 3);
+        assert_eq!( (z.num.clone(), z.den.clone()), (Integer::from(-1), Integer::from(6)) );
+        let z = f(1, -2) - f(-1, -3);
+        assert_eq!( (z.num.clone(), z.den.clone()), (Integer::from(-5), Integer::from(6)) );
+        let z = f(1, -2) * f(-2, 3);
+        assert_eq!( (z.num.clone(), z.den.clone()), (Integer::from(1), Integer::from(3)) );
+        let z = f(1, 2) / f(-1, 4);
+        assert_eq!( (z.num.clone(), z.den.clone()), (Integer::from(-2), Integer::from(1)) );
+        let mut z = f(3, -4);
+        z += f(1, 4);
+        assert_eq!( z, f(-1, 2) );
+        assert!( z.den > 0 );
+        assert_eq!( -f(1, -2), f(1, 2) );
+    }
+
+    #[test]
+    fn integer_parts() {
+        for (x, fl, ce, ro) in [ (f(7, 2), 3, 4, 4), (f(-7, 2), -4, -3, -4), (f(7, -2), -4, -3, -4),
+                                 (f(-7, -2), 3, 4, 4), (f(5, 3), 1, 2, 2), (f(-6, 3), -2, -2, -2) ] {
+            assert_eq!( (x.floor(), x.ceil(), x.round()), (int(fl), int(ce), int(ro)), "{}", x );
+        }
+        for x in [ f(1, 0), f(-1, 0), f(0, 0) ] {
+            assert_eq!( (x.floor(), x.ceil(), x.round()), (None, None, None) );
         }
     }
+
+    fn parsed( s: &str ) -> Frac {
+        s.parse::<Frac>().unwrap_or_else( |e| panic!( "{}", e ) )
+    }
+
+    #[test]
+    fn parse_round_trips() {
+        for x in [ f(355, 113), f(-355, 113), f(0, 1), f(7, 1), f(-1, 3) ] {
+            assert_eq!( parsed( &x.to_string() ), x );
+        }
+        assert_eq!( parsed( " 355 / 113 " ), f(355, 113) );
+        assert_eq!( parsed( "-6/-4" ), f(3, 2) );
+        assert_eq!( parsed( "+2/-4" ), f(-1, 2) );
+        assert_eq!( parsed( "3.14159" ), f(314159, 100000) );
+        assert_eq!( parsed( "-0.5" ), f(-1, 2) );
+        assert_eq!( parsed( ".25" ), f(1, 4) );
+        assert_eq!( parsed( "2." ), f(2, 1) );
+        assert_eq!( parsed( "1.5e-7" ), f(3, 20_000_000) );
+        assert_eq!( parsed( "-1.5E3" ), f(-1500, 1) );
+        assert_eq!( parsed( "0.(142857)" ), f(1, 7) );
+        assert_eq!( parsed( "0.1(6)" ), f(1, 6) );
+        assert_eq!( parsed( "1.5/2e3" ), f(3, 4000) );
+        let z = parsed( "12/8" );
+        assert_eq!( (z.num, z.den), (Integer::from(3), Integer::from(2)) );
+    }
+
+    #[test]
+    fn parse_rejects() {
+        for s in [ "", "x", "1/", "/2", "1.2.3", "1e", "e5", "--1", "0.(", "0.()", "1,5", "1/2/3", "0x10" ] {
+            assert_eq!( s.parse::<Frac>(), Err( FracError::Parse( s.to_string() ) ), "{:?}", s );
+        }
+        assert_eq!( "1e2000000000".parse::<Frac>(), Err( FracError::Exponent(2_000_000_000) ) );
+        assert_eq!( "1/1e-2000000".parse::<Frac>(), Err( FracError::Exponent(-2_000_000) ) );
+    }
+
+    #[test]
+    fn floats_exactly() {
+        let from = |a: f64| Frac::try_from( a ).unwrap();
+        assert_eq!( from( 0.1 ), f(3602879701896397, 1 << 55) );
+        assert_eq!( from( -2.5 ), f(-5, 2) );
+        assert_eq!( from( 0.0 ), f(0, 1) );
+        assert_eq!( from( -0.0 ), f(0, 1) );
+        assert_eq!( from( 2f64.powi(100) ), Frac::from( Integer::from(1) << 100u32 ) );
+        // the least subnormal, and the greatest one
+        assert_eq!( from( f64::from_bits(1) ), Frac::from_int( Integer::from(1), Integer::from(1) << 1074u32 ) );
+        let sub = from( f64::MIN_POSITIVE - f64::from_bits(1) );
+        assert_eq!( sub, Frac::from_int( (Integer::from(1) << 52u32) - 1u32, Integer::from(1) << 1074u32 ) );
+        assert_eq!( from( f64::MAX ), Frac::from( ((Integer::from(1) << 53u32) - 1u32) << 971u32 ) );
+        assert_eq!( from( f64::INFINITY ), f(1, 0) );
+        assert_eq!( from( f64::NEG_INFINITY ), f(-1, 0) );
+        assert_eq!( Frac::try_from( f64::NAN ), Err( FracError::NaN ) );
+        for a in [ 0.1, -2.5, 1e-30, 6.02e23, 1.0 / 3.0 ] {
+            let z = from( a );
+            assert_eq!( z.num.to_f64() / z.den.to_f64(), a );
+        }
+    }
+
+    fn pi_to( bits: u32 ) -> RegFrac<Frac> {
+        // the finished expansion of a convergent of pi of at least so many bits
+        let mut k = KFrac::from( math_consts::pi_ab );
+        while k.frac2.den.significant_bits() < bits {
+            k.prop_bs( 64 );
+        }
+        let mut r = RegFrac::from( Frac::from_int( k.frac2.num.clone(), k.frac2.den.clone() ) );
+        r.red( false );
+        r
+    }
+
+    fn brute( x: &Frac, max_den: i128, second: bool ) -> Vec<Frac> {
+        // the record breakers of |x - p/q| (or of |qx - p|) for q = 1, 2, ..., with p the nearest
+        let mut best: Vec<Frac> = Vec::new();
+        let mut record: Option<Frac> = None;
+        for q in 1..=max_den {
+            let p = (x * &f(q, 1)).round().unwrap();
+            let y = Frac::from_int( p, Integer::from(q) );
+            let mut e = (x - &y).abs();
+            if second {
+                e *= f(q, 1);
+            }
+            if record.as_ref().is_none_or( |r| e < *r ) {
+                record = Some(e);
+                best.push(y);
+            }
+        }
+        best
+    }
+
+    #[test]
+    fn best_approximations_of_pi() {
+        let x = pi_to( 128 );
+        let best = |m: i128| x.best_approx( &Integer::from(m) ).map( |(y, _)| y );
+        assert_eq!( best(113), Some( f(355, 113) ) );
+        assert_eq!( best(112), Some( f(333, 106) ) );
+        assert_eq!( best(100), Some( f(311, 99) ) );
+        assert_eq!( best(57), Some( f(179, 57) ) );
+        assert_eq!( best(56), Some( f(22, 7) ) );
+        assert_eq!( best(7), Some( f(22, 7) ) );
+        assert_eq!( best(1), Some( f(3, 1) ) );
+        assert_eq!( best(0), None );
+        let (y, e) = x.best_approx( &Integer::from(113) ).unwrap();
+        let v = x.convergents().pop().unwrap();
+        assert_eq!( e, (&v - &y).abs() );
+    }
+
+    #[test]
+    fn best_approximations_match_brute_force() {
+        // pi, and sqrt 2 for the semiconvergents with m = a/2 (all its terms after the first are 2),
+        // which are best approximations of the first kind or not depending on the rest
+        let mut k = KFrac::from( math_consts::sqrt2_ab );
+        k.prop_bs( 200 );
+        let mut sqrt2 = RegFrac::from( Frac::from_int( k.frac2.num.clone(), k.frac2.den.clone() ) );
+        sqrt2.red( false );
+        for x in [ pi_to( 128 ), sqrt2 ] {
+            let v = x.convergents().pop().unwrap();
+            let first: Vec<Frac> = x.best_first_kind().into_iter().map( |(y, _)| y ).filter( |y| y.den <= 3000 ).collect();
+            assert_eq!( first, brute( &v, 3000, false ) );
+            let second: Vec<Frac> = x.best_second_kind().into_iter().map( |(y, _)| y ).filter( |y| y.den <= 3000 ).collect();
+            assert_eq!( second, brute( &v, 3000, true ) );
+        }
+    }
+
+    #[test]
+    fn best_approximations_at_the_ends() {
+        // an exact value, whose own tape decides everything
+        let mut x = RegFrac::from( f(355, 113) );
+        x.red( false );
+        assert_eq!( x.best_approx( &Integer::from(113) ), Some( (f(355, 113), f(0, 1)) ) );
+        assert_eq!( x.best_approx( &Integer::from(10_000) ), Some( (f(355, 113), f(0, 1)) ) );
+        assert_eq!( x.best_first_kind().last().map( |(y, e)| (y.clone(), e.clone()) ), Some( (f(355, 113), f(0, 1)) ) );
+        // an unfinished tape only tells up to its own denominators
+        let mut x = RegFrac::from( KFrac::from( math_consts::pi_ab ) );
+        x.red( 5, None );
+        assert_eq!( x.tape[..5], [3, 7, 15, 1, 292] );
+        assert_eq!( x.best_approx( &Integer::from(1000) ).map( |(y, _)| y ), Some( f(355, 113) ) );
+        assert_eq!( x.best_approx( &Integer::from(1_000_000) ), None );
+        // simplest in closed intervals, of equal ends too
+        assert_eq!( Frac::simplest( &f(355, 113), &f(710, 226) ), f(355, 113) );
+        assert_eq!( Frac::simplest( &f(1, 3), &f(1, 2) ), f(1, 2) );
+        assert_eq!( Frac::simplest( &f(5, 11), &f(3, 7) ), f(3, 7) );
+        assert_eq!( Frac::simplest( &f(2, 1), &f(3, 1) ), f(2, 1) );
+        assert_eq!( Frac::simplest( &f(5, 2), &f(1, 0) ), f(3, 1) );
+        assert_eq!( Frac::simplest( &f(0, 1), &f(1, 3) ), f(0, 1) );
+        assert_eq!( Frac::simplest( &f(31415, 10000), &f(31416, 10000) ), f(333, 106) );
+    }
 }
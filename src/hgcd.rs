@@ -0,0 +1,244 @@
+// Divide and conquer reduction of huge rationals, in the manner of Schönhage's half-gcd: instead of
+// one full size step per digit (or term), the leading bits of the fraction are cut out as an
+// interval known to contain it, the digits on which the whole interval agrees are found recursively
+// from the leading bits of that, and the 2x2 transform they amount to is applied to the full
+// numbers in one go. The work is then a few multiplications per halving of the size rather than
+// one subtraction per digit, which pays off from some thousands of bits on.
+
+use std::borrow::Cow;
+use crate::int::Integer;
+use crate::fractions::{Frac, RegFrac, RegFracOps};
+use crate::logs::{KLog, KLogOps};
+use crate::real::{self, Real};
+
+// below this many bits the intervals are reduced digit by digit
+const BASE: u32 = 256;
+// and below these many the fractions themselves, where the plain reductions are faster (red1 of a
+// RegFrac being a single division, it holds out longer)
+const KLOG_CUTOFF: u32 = 1 << 14;
+const REG_FRAC_CUTOFF: u32 = 1 << 16;
+
+#[derive(Default)]
+struct Lead {
+    // The ends of an interval and the transform N = [a, b, c, d] applied to them so far, as the
+    // columns of m = [lo, hi, a, b | lo', hi', c, d] (numerators, then denominators), on which the
+    // digit and term maps act as on the rows of a Mobius
+    m: [Integer; 8],
+}
+
+impl Lead {
+    fn new( lo: [Integer; 2], hi: [Integer; 2], n: [Integer; 4] ) -> Self {
+        let ([l0, l1], [h0, h1], [a, b, c, d]) = (lo, hi, n);
+        Lead { m: [l0, h0, a, b, l1, h1, c, d] }
+    }
+    fn split( self ) -> ([Integer; 2], [Integer; 2], [Integer; 4]) {
+        let [l0, h0, a, b, l1, h1, c, d] = self.m;
+        ( [l0, l1], [h0, h1], [a, b, c, d] )
+    }
+}
+
+impl Real for Lead {
+    fn enclosure( &self ) -> (Cow<'_, Frac>, Cow<'_, Frac>) {
+        let m = &self.m;
+        ( Cow::Owned( Frac::from_int( m[0].clone(), m[4].clone() ) ), Cow::Owned( Frac::from_int( m[1].clone(), m[5].clone() ) ) )
+    }
+    fn refine( &mut self ) {
+        // the leading bits are all there is
+    }
+}
+
+impl KLogOps for Lead {
+    fn iota( &mut self ) {
+        real::iota_rows( &mut self.m );
+    }
+    fn theta( &mut self ) {
+        real::theta_rows( &mut self.m, 1 );
+    }
+    fn theta_run( &mut self, s: u32 ) {
+        real::theta_rows( &mut self.m, s );
+    }
+    fn gcd_div( &mut self ) {
+        real::gcd_rows( &mut self.m );
+    }
+    fn pow2_div( &mut self ) {
+        real::pow2_rows( &mut self.m );
+    }
+    fn bits( &self ) -> u64 {
+        real::bits_rows( &self.m )
+    }
+}
+
+impl RegFracOps for Lead {
+    fn term( &mut self, t: &Integer ) {
+        real::left( &mut self.m, [ Integer::from(0), Integer::from(1), Integer::from(1), Integer::from( -t ) ] );
+    }
+}
+
+fn klog_base( l: Lead ) -> (Vec<u8>, Lead) {
+    let mut k = KLog::from( l );
+    while k.digits( usize::MAX ) > 0 {}
+    ( k.tape, k.remainder )
+}
+
+fn reg_frac_base( l: Lead ) -> (Vec<u128>, Lead) {
+    let mut r = RegFrac::from( l );
+    while r.next_term() {}
+    ( r.tape, r.remainder )
+}
+
+fn cut( lo: &[Integer; 2], hi: &[Integer; 2], h: u32 ) -> ([Integer; 2], [Integer; 2]) {
+    // The interval of the leading bits (all but h) of the ends, rounded outwards. The ends have
+    // non-negative terms and either order, as the maps reverse it.
+    let (l, u) = if Integer::from( &lo[0] * &hi[1] ) <= Integer::from( &hi[0] * &lo[1] ) { (lo, hi) } else { (hi, lo) };
+    ( [ Integer::from( &l[0] >> h ), Integer::from( &l[1] >> h ) + 1 ],
+      [ Integer::from( &u[0] >> h ) + 1, Integer::from( &u[1] >> h ) ] )
+}
+
+fn lead<D>( mut lo: [Integer; 2], mut hi: [Integer; 2], base: fn( Lead ) -> (Vec<D>, Lead) ) -> (Vec<D>, [Integer; 4]) {
+    // The digits (or terms) on which all of [lo, hi] agrees, and the transform they apply: those of
+    // the leading half of the bits first, recursively, then again for what is left of the interval
+    let mut tape = Vec::new();
+    let mut n = [1, 0, 0, 1].map( Integer::from );
+    loop {
+        let bits = lo.iter().chain( hi.iter() ).map( |c| c.significant_bits() ).max().unwrap_or(0);
+        if bits <= BASE {
+            let (t, l) = base( Lead::new( lo, hi, n ) );
+            tape.extend( t );
+            n = l.split().2;
+            break;
+        }
+        let (l, u) = cut( &lo, &hi, bits / 2 );
+        let (t, s) = lead( l, u, base );
+        if t.is_empty() {
+            break;
+        }
+        tape.extend( t );
+        let mut m = Lead::new( lo, hi, n ).m;
+        real::left( &mut m, s );
+        (lo, hi, n) = Lead { m }.split();
+        // the powers of two that theta leaves, of the ends one by one but of the transform as a whole
+        for c in [ &mut lo[..], &mut hi[..], &mut n[..] ] {
+            real::pow2_rows( c );
+        }
+    }
+    ( tape, n )
+}
+
+fn block<D>( x: &mut Frac, cutoff: u32, base: fn( Lead ) -> (Vec<D>, Lead) ) -> Vec<D> {
+    // The digits (or terms) found from the leading half of x, which is reduced by them. None when
+    // x is small enough for the plain reduction, or the leading bits do not tell.
+    let bits = x.num.significant_bits().max( x.den.significant_bits() );
+    if bits <= cutoff {
+        return Vec::new();
+    }
+    let (lo, hi) = cut( &[ x.num.clone(), x.den.clone() ], &[ x.num.clone(), x.den.clone() ], bits / 2 );
+    let (tape, n) = lead( lo, hi, base );
+    if !tape.is_empty() {
+        let mut m = [ std::mem::take( &mut x.num ), std::mem::take( &mut x.den ) ];
+        real::left( &mut m, n );
+        real::pow2_rows( &mut m );
+        let [num, den] = m;
+        *x = Frac::from_int( num, den );
+    }
+    tape
+}
+
+impl KLog<Frac> {
+    pub fn red_hgcd( &mut self ) {
+        // As red, in blocks of digits from the leading bits, and with red_run for what they leave
+        if !self.remainder.atleast1() {
+            println!("\u{274c} Can't reduce numbers (Frac) less than 1!");
+            return;
+        }
+        self.remainder = real::upright( Cow::Borrowed( &self.remainder ) );
+        while self.remainder.finite() {
+            let tape = block( &mut self.remainder, KLOG_CUTOFF, klog_base );
+            if tape.is_empty() {
+                self.red_run();
+            } else {
+                self.tape.extend( tape );
+            }
+            self.norm.step( &mut self.remainder );
+            if let Err(e) = self.stream() {
+                println!("\u{274c} KLog<Frac>: {}", e);
+                return;
+            }
+        }
+        self.done = true;
+        if let Err(e) = self.close_sink() {
+            println!("\u{274c} KLog<Frac>: {}", e);
+        }
+    }
+}
+
+impl RegFrac<Frac> {
+    pub fn red_hgcd( &mut self ) {
+        // As red, in blocks of terms from the leading bits, and with red1 for what they leave
+        self.remainder = real::upright( Cow::Borrowed( &self.remainder ) );
+        if self.remainder.num < 0 {
+            println!("\u{274c} Can't reduce negative numbers (Frac)!");
+            return;
+        }
+        while self.remainder.finite() {
+            let tape = block( &mut self.remainder, REG_FRAC_CUTOFF, reg_frac_base );
+            if tape.is_empty() {
+                self.red1();
+            } else {
+                self.tape.extend( tape );
+            }
+            self.norm.step( &mut self.remainder );
+            if let Err(e) = self.stream() {
+                println!("\u{274c} RegFrac<Frac>: {}", e);
+                return;
+            }
+        }
+        if let Err(e) = self.close_sink() {
+            println!("\u{274c} RegFrac<Frac>: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::KFrac;
+    use crate::math_consts::pi_ab;
+
+    fn convergent( bits: u32 ) -> Frac {
+        // a convergent of pi of at least so many bits
+        let mut k = KFrac::from( pi_ab );
+        while k.frac2.den.significant_bits() < bits {
+            k.prop_bs( 256 );
+        }
+        Frac::from_int( k.frac2.num.clone(), k.frac2.den.clone() )
+    }
+
+    fn negated( x: &Frac ) -> Frac {
+        Frac::from_int( Integer::from( -&x.num ), Integer::from( -&x.den ) )
+    }
+
+    #[test]
+    fn klog_blocks_match_red() {
+        let x = convergent( 2 * KLOG_CUTOFF );
+        let mut plain = KLog::from( x.clone() );
+        plain.red( false );
+        for y in [ x.clone(), negated(&x) ] {
+            let mut fast = KLog::from( y );
+            fast.red_hgcd();
+            assert!( fast.done );
+            assert_eq!( fast.tape, plain.tape );
+        }
+    }
+
+    #[test]
+    fn reg_frac_blocks_match_red() {
+        let x = convergent( REG_FRAC_CUTOFF + REG_FRAC_CUTOFF / 4 );
+        let mut plain = RegFrac::from( x.clone() );
+        plain.red( false );
+        for y in [ x.clone(), negated(&x) ] {
+            let mut fast = RegFrac::from( y );
+            fast.red_hgcd();
+            assert_eq!( fast.tape, plain.tape );
+        }
+    }
+}
@@ -0,0 +1,79 @@
+// The big integers behind Frac, KFrac, the KLogOps impls and Series. With the "gmp" feature (the
+// default) Integer is rug's, on GMP; without it, "bigint" gives a pure-Rust one on num-bigint, for
+// builds without a C toolchain. Both share the operators, comparisons and the rug method names the
+// library uses (find_one, significant_bits, cmp0, cmp_abs, ...); IntOps adds what rug only offers as
+// incomplete computations.
+
+#[cfg(feature = "gmp")]
+pub use rug::{Integer, ops::Pow};
+#[cfg(not(feature = "gmp"))]
+pub use crate::bigint::{Integer, Pow};
+
+#[cfg(not(any(feature = "gmp", feature = "bigint")))]
+compile_error!( "k-log needs a big integer backend, enable the feature \"gmp\" or \"bigint\"" );
+
+pub trait IntOps: Sized {
+    fn gcd_of( &self, other: &Self ) -> Self;
+    fn div_rem_of( &self, d: &Self ) -> (Self, Self);       // quotient rounded towards zero
+    fn div_rem_floor_of( &self, d: &Self ) -> (Self, Self);
+    fn div_rem_ceil_of( &self, d: &Self ) -> (Self, Self);
+    fn div_rem_round_of( &self, d: &Self ) -> (Self, Self); // to the nearest, ties away from zero
+    fn abs_of( &self ) -> Self;
+    fn pow_of( &self, e: u32 ) -> Self;
+    fn sqrt_of( &self ) -> Self;                            // floor of the square root, for self >= 0
+    fn root_of( &self, n: u32 ) -> Self;                    // floor of the n-th root, for self >= 0
+    fn magnitude_be( &self ) -> Vec<u8>;                    // |self| as big-endian bytes, none for 0
+    fn from_magnitude_be( b: &[u8] ) -> Self;
+}
+
+#[cfg(feature = "gmp")]
+impl IntOps for Integer {
+    fn gcd_of( &self, other: &Self ) -> Self {
+        use rug::Complete;
+        self.gcd_ref( other ).complete()
+    }
+    fn div_rem_of( &self, d: &Self ) -> (Self, Self) {
+        use rug::Complete;
+        self.div_rem_ref( d ).complete()
+    }
+    fn div_rem_floor_of( &self, d: &Self ) -> (Self, Self) {
+        use rug::Complete;
+        self.div_rem_floor_ref( d ).complete()
+    }
+    fn div_rem_ceil_of( &self, d: &Self ) -> (Self, Self) {
+        use rug::Complete;
+        self.div_rem_ceil_ref( d ).complete()
+    }
+    fn div_rem_round_of( &self, d: &Self ) -> (Self, Self) {
+        use rug::Complete;
+        self.div_rem_round_ref( d ).complete()
+    }
+    fn abs_of( &self ) -> Self {
+        Integer::from( self.abs_ref() )
+    }
+    fn pow_of( &self, e: u32 ) -> Self {
+        Integer::from( self.pow( e ) )
+    }
+    fn sqrt_of( &self ) -> Self {
+        Integer::from( self.sqrt_ref() )
+    }
+    fn root_of( &self, n: u32 ) -> Self {
+        Integer::from( self.root_ref( n ) )
+    }
+    fn magnitude_be( &self ) -> Vec<u8> {
+        self.to_digits::<u8>( rug::integer::Order::Msf )
+    }
+    fn from_magnitude_be( b: &[u8] ) -> Self {
+        Integer::from_digits( b, rug::integer::Order::Msf )
+    }
+}
+
+pub fn log2( x: &Integer ) -> f64 {
+    // log_2 |x| to f64 precision, also beyond the range of f64 (-inf for 0)
+    let b = x.significant_bits();
+    if b <= 64 {
+        x.to_f64().abs().log2()
+    } else {
+        Integer::from( x >> (b - 64) ).to_f64().abs().log2() + (b - 64) as f64
+    }
+}
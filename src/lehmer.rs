@@ -0,0 +1,257 @@
+// Lehmer's trick for the reductions of KFracs: the digits (or terms) of the enclosure are almost
+// always decided by the leading bits of its ends, so those are rounded outwards to a word, the
+// digits on which the rounded interval agrees are found in word arithmetic, and the transform they
+// make up is applied to the convergents in one go, instead of full size comparisons and updates per
+// digit. Where the words do not tell, the reductions carry on as before.
+
+use std::borrow::Cow;
+use crate::int::Integer;
+use crate::fractions::{Frac, KFrac, RegFrac};
+use crate::logs::KLog;
+use crate::real;
+
+// bits kept of the ends, so that cross products fit a u128
+const WORD: u32 = 63;
+// bound on the entries of the transform, and on the terms of the ends as they grow through theta
+const ENTRY: i128 = 1 << 62;
+const GROWN: u128 = 1 << 124;
+
+struct Words {
+    // The rounded ends (num, den), and the transform [a, b, c, d] taking the numbers as they were
+    // to the rest: (num, den) -> (a num + b den, c num + d den)
+    ends: [(u128, u128); 2],
+    n: [i128; 4],
+}
+
+impl Words {
+    fn new( e1: &Frac, e2: &Frac ) -> Option<Self> {
+        // The interval [lo, hi] of words enclosing both ends: each is cut to its leading bits and
+        // rounded down (and up), then the lower and the upper of those are kept. None for negative
+        // ends, which the plain reductions report.
+        let (e1, e2) = ( real::upright( Cow::Borrowed(e1) ), real::upright( Cow::Borrowed(e2) ) );
+        if e1.num < 0 || e2.num < 0 {
+            return None;
+        }
+        let word = |e: &Frac| {
+            // each end by its own shift, as the convergents can differ much in scale
+            let h = e.num.significant_bits().max( e.den.significant_bits() ).saturating_sub( WORD );
+            Some(( Integer::from( &e.num >> h ).to_u128()?, Integer::from( &e.den >> h ).to_u128()? ))
+        };
+        let ((p1, q1), (p2, q2)) = ( word( &e1 )?, word( &e2 )? );
+        let lo = if p1 * (q2 + 1) <= p2 * (q1 + 1) { (p1, q1 + 1) } else { (p2, q2 + 1) };
+        let hi = if (p1 + 1) * q2 >= (p2 + 1) * q1 { (p1 + 1, q1) } else { (p2 + 1, q2) };
+        Some( Words { ends: [lo, hi], n: [1, 0, 0, 1] } )
+    }
+
+    fn step( &mut self, rows: [i128; 4], halve: bool ) -> bool {
+        // n <- rows n, when the entries stay within bounds (halved if they all allow it)
+        let [ra, rb, rc, rd] = rows;
+        let [a, b, c, d] = self.n;
+        let entry = |x: i128, y: i128, u: i128, v: i128| x.checked_mul(u)?.checked_add( y.checked_mul(v)? );
+        let m = [ entry( ra, rb, a, c ), entry( ra, rb, b, d ), entry( rc, rd, a, c ), entry( rc, rd, b, d ) ];
+        let Some(mut m) = m.into_iter().collect::<Option<Vec<i128>>>() else {
+            return false;
+        };
+        if halve && m.iter().all( |x| x % 2 == 0 ) {
+            m.iter_mut().for_each( |x| *x /= 2 );
+        }
+        if m.iter().any( |x| x.abs() >= ENTRY ) {
+            return false;
+        }
+        self.n = [m[0], m[1], m[2], m[3]];
+        true
+    }
+
+    fn digits( &mut self, max: usize ) -> Vec<u8> {
+        // theta while both ends are >= 2, iota while both are in [1, 2)
+        let mut tape = Vec::new();
+        while tape.len() < max {
+            let [(p1, q1), (p2, q2)] = self.ends;
+            if q1 >= GROWN || q2 >= GROWN {
+                break;
+            }
+            if p1 >= 2 * q1 && p2 >= 2 * q2 {
+                // y/2, with the numerator halved where it can be, as in theta
+                if !self.step( [1, 0, 0, 2], true ) {
+                    break;
+                }
+                for e in self.ends.iter_mut() {
+                    *e = if e.0 % 2 == 0 { (e.0 / 2, e.1) } else { (e.0, 2 * e.1) };
+                }
+                tape.push(1);
+            } else if q1 <= p1 && p1 < 2 * q1 && q2 <= p2 && p2 < 2 * q2 {
+                // 1/(y - 1)
+                if !self.step( [0, 1, 1, -1], false ) {
+                    break;
+                }
+                for e in self.ends.iter_mut() {
+                    *e = (e.1, e.0 - e.1);
+                }
+                tape.push(0);
+            } else {
+                break;
+            }
+        }
+        tape
+    }
+
+    fn terms( &mut self, max: usize ) -> Vec<u128> {
+        // the common floor of the ends, while they have one
+        let mut tape = Vec::new();
+        while tape.len() < max {
+            let [(p1, q1), (p2, q2)] = self.ends;
+            if q1 == 0 || q2 == 0 || p1 / q1 != p2 / q2 {
+                break;
+            }
+            let t = p1 / q1;
+            // 1/(y - t)
+            if t >= ENTRY as u128 || !self.step( [0, 1, 1, -(t as i128)], false ) {
+                break;
+            }
+            for e in self.ends.iter_mut() {
+                *e = (e.1, e.0 - t * e.1);
+            }
+            tape.push(t);
+        }
+        tape
+    }
+}
+
+fn apply( k: &mut KFrac, n: [i128; 4] ) {
+    // the transform on both convergents, i.e. on the rows of M = [frac2 frac1]
+    let [a, b, c, d] = n;
+    for f in [&mut k.frac1, &mut k.frac2] {
+        let num = Integer::from( &f.num * a ) + Integer::from( &f.den * b );
+        let den = Integer::from( &f.num * c ) + Integer::from( &f.den * d );
+        (f.num, f.den) = (num, den);
+    }
+}
+
+impl KLog<KFrac> {
+    pub fn red_lehmer( &mut self, max: usize ) -> usize {
+        // At most 'max' digits from the leading words of the enclosure, their number is returned
+        let (tape, n) = {
+            let (e1, e2) = self.remainder.enclosure();
+            let Some(mut w) = Words::new( &e1, &e2 ) else {
+                return 0;
+            };
+            ( w.digits( max ), w.n )
+        };
+        if !tape.is_empty() {
+            apply( &mut self.remainder, n );
+            self.tape.extend( tape.iter() );
+        }
+        tape.len()
+    }
+}
+
+impl RegFrac<KFrac> {
+    pub fn red_lehmer( &mut self, max: usize ) -> usize {
+        // As for KLog, with at most 'max' terms
+        let (tape, n) = {
+            let (e1, e2) = self.remainder.enclosure();
+            let Some(mut w) = Words::new( &e1, &e2 ) else {
+                return 0;
+            };
+            ( w.terms( max ), w.n )
+        };
+        if !tape.is_empty() {
+            apply( &mut self.remainder, n );
+            self.tape.extend( tape.iter() );
+        }
+        tape.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Mobius;
+    use crate::math_consts::{e_ab, pi_ab, zeta3_ab};
+
+    fn ends() -> Vec<(Frac, Frac)> {
+        // convergent pairs, ends at 1/0 and ends too large for their denominators to survive the
+        // cut, both making an upper word with q = 0
+        let mut v = Vec::new();
+        for f in [pi_ab, e_ab, zeta3_ab] {
+            let mut k = KFrac::from( f );
+            v.push( (k.frac1.clone(), k.frac2.clone()) );
+            for _ in 0..6 {
+                k.prop_bs( 7 );
+                v.push( (k.frac1.clone(), k.frac2.clone()) );
+            }
+        }
+        let big = Integer::from(1) << 100u32;
+        v.push( (Frac::from_int( Integer::from( &big + 12345 ), Integer::from(1) ), Frac::from_int( Integer::from( &big + 99999 ), Integer::from(1) )) );
+        v.push( (Frac::from_i128( 1, 0 ), Frac::from_int( big, Integer::from(3) )) );
+        v.push( (Frac::from_i128( 355, 113 ), Frac::from_i128( 1, 0 )) );
+        v
+    }
+
+    fn finite( x: &Frac ) -> Frac {
+        // 1/0 stands for all large enough values, of which this is one
+        if x.finite() { x.clone() } else { Frac::from_int( Integer::from(1) << 120u32, Integer::from(1) ) }
+    }
+
+    #[test]
+    fn word_digits_hold_for_both_ends() {
+        let mut open = 0;
+        for (e1, e2) in ends() {
+            let Some(mut w) = Words::new( &e1, &e2 ) else {
+                continue;
+            };
+            if w.ends[1].1 == 0 {
+                open += 1;
+            }
+            let tape = w.digits( usize::MAX );
+            for e in [&e1, &e2] {
+                let mut k = KLog::from( finite(e) );
+                k.red( false );
+                assert!( k.tape.len() >= tape.len() && k.tape[..tape.len()] == tape[..], "{} in [{}, {}]", e, e1, e2 );
+            }
+        }
+        assert!( open >= 3 );
+    }
+
+    #[test]
+    fn word_terms_hold_for_both_ends() {
+        for (e1, e2) in ends() {
+            let Some(mut w) = Words::new( &e1, &e2 ) else {
+                continue;
+            };
+            let tape = w.terms( usize::MAX );
+            for e in [&e1, &e2] {
+                let mut r = RegFrac::from( finite(e) );
+                r.red( false );
+                assert!( r.tape.len() >= tape.len() && r.tape[..tape.len()] == tape[..], "{} in [{}, {}]", e, e1, e2 );
+            }
+        }
+    }
+
+    #[test]
+    fn words_take_over_from_the_top() {
+        let mut k = KLog::from( KFrac::from( pi_ab ) );
+        k.remainder.prop_bs( 100 );
+        assert!( k.red_lehmer( usize::MAX ) > 0 );
+        let mut r = RegFrac::from( KFrac::from( pi_ab ) );
+        r.remainder.prop_bs( 100 );
+        assert!( r.red_lehmer( usize::MAX ) > 0 );
+    }
+
+    #[test]
+    fn reductions_match_red_real() {
+        for f in [pi_ab, e_ab, zeta3_ab] {
+            let mut fast = KLog::from( KFrac::from( f ) );
+            fast.red( 3000, None, false );
+            let mut generic = KLog::from( Mobius::from( KFrac::from( f ) ) );
+            generic.red_real( 3000 );
+            assert_eq!( fast.tape[..3000], generic.tape[..3000] );
+
+            let mut fast = RegFrac::from( KFrac::from( f ) );
+            fast.red( 500, None );
+            let mut generic = RegFrac::from( Mobius::from( KFrac::from( f ) ) );
+            generic.red_real( 500 );
+            assert_eq!( fast.tape[..500], generic.tape[..500] );
+        }
+    }
+}
@@ -1,15 +1,50 @@
 // Implementation of Continued Logarithms for ordinary and continued fractions
 
-use rug::Integer;
+use budget::Meter;
+use progress::report;
+use std::time::Instant;
 
 pub mod math_consts;
+mod int;
+#[cfg(not(feature = "gmp"))]
+mod bigint;
 mod fractions;
 mod series;
 mod logs;
+mod norm;
+mod transforms;
+mod budget;
+mod progress;
+mod stream;
+#[cfg(feature = "capi")]
+pub mod capi;
+#[cfg(feature = "python")]
+mod python;
+mod validate;
+mod lookup;
+mod real;
+mod expr;
+mod root;
+mod hgcd;
+mod lehmer;
+#[cfg(feature = "serde")]
+mod ser;
 
-pub use fractions::{Frac, KFrac, RegFrac, RegFracOps};
-pub use logs::{KLog, KLogOps};
-pub use series::{Series};
+pub use int::{Integer, IntOps};
+pub use fractions::{Frac, FracError, Gen, KFrac, RegFrac, RegFracOps, Tail};
+pub use logs::{KLog, KLogOps, KLogPrefix, KLogPrefixes};
+pub use series::{Series, qd};
+pub use norm::{Norm, NormStats, Normaliser};
+pub use validate::Validation;
+pub use lookup::{Candidate, Lookup, Match};
+pub use real::{Mobius, Pull, Real, Trimmed};
+pub use expr::{Bihom, Expr};
+pub use root::{KLogRoot, RegFracRoot, Root};
+pub use budget::{Budget, Cancel, RedError};
+pub use progress::{Observer, Print, Progress, Stage, Watch};
+pub use stream::{Format, Sink};
+#[cfg(feature = "serde")]
+pub use ser::{KFracDesc, SeriesState};
 
 impl KLogOps for Frac {
     fn iota( &mut self) {
@@ -27,6 +62,18 @@ impl KLogOps for Frac {
     fn gcd_div(&mut self) {
         self.gcd_div();
     }
+    fn pow2_div(&mut self) {
+        self.pow2_div();
+    }
+    fn bits(&self) -> u64 {
+        self.bits()
+    }
+    fn theta_run( &mut self, s: u32 ) {
+        // as in theta, powers of two leave the numerator first, and the rest go to the denominator
+        let z = self.num.find_one(0).unwrap_or(s).min(s);
+        self.num >>= z;
+        self.den <<= s - z;
+    }
 }
 
 impl KLog<Frac> {
@@ -49,16 +96,38 @@ impl KLog<Frac> {
             panic!();
         }
     }
+    pub fn red_run( &mut self ) {
+        // A whole run of '1's closed by a '0' (one entry of gen_tape), with the length of the run
+        // read off the bit lengths instead of comparing and shifting digit by digit
+        if !self.remainder.atleast1() {
+            println!("\u{274c} Number fell below 1 during reduction!");
+            panic!();
+        }
+        if let Some(s) = self.remainder.log2_floor() {
+            self.remainder.theta_run(s);
+            self.tape.resize( self.tape.len() + s as usize, 1 );
+            self.remainder.iota();
+            self.tape.push(0);
+        }
+    }
     pub fn red( &mut self, verbose: bool ) {
         if !self.remainder.atleast1() {
             println!("\u{274c} Can't reduce numbers (Frac) less than 1!");
             return;
         }
         while self.remainder.finite() {
-            self.red1();
+            self.red_run();
+            self.norm.step( &mut self.remainder );
             if verbose { println!("{}", self); }
+            if let Err(e) = self.stream() {
+                println!("\u{274c} KLog<Frac>: {}", e);
+                return;
+            }
         }
         self.done = true;
+        if let Err(e) = self.close_sink() {
+            println!("\u{274c} KLog<Frac>: {}", e);
+        }
     }
 
     fn reconstruct( mut x: Frac, s: &str ) -> Option<Frac> {
@@ -105,8 +174,97 @@ impl KLog<Frac> {
         }
         None
     }
+
+    const MAX_RUN: usize = 1 << 24;
+
+    fn enclose( tape: &str, open: bool ) -> Option<(Frac,Frac)> {
+    // Exact value of a finished tape, or the interval spanned by all remainders in [1, inf]
+        let y1 = Self::reconstruct( Frac::from_i128(1,0), tape )?;
+        if open {
+            let y2 = Self::reconstruct( Frac::from(1), tape )?;
+            Some((y1, y2))
+        } else {
+            Some((y1.clone(), y1))
+        }
+    }
+
+    pub fn parse_gen( input: &str ) -> Result<(Frac,Frac), ParseError> {
+    // Parser for the shorthand notation of gen_tape, e.g. "[3, 0, 2]", where every run of '1's is
+    // closed by a '0', so the number is fully determined. A trailing ", ..." as in "[3, 0, 2, ...]"
+    // (see gen_str) marks an unfinished expansion: the last run is left open and an interval is
+    // produced. Runs longer than MAX_RUN are refused rather than spelled out.
+        let mut body = input.trim();
+        body = body.strip_prefix('[').unwrap_or(body);
+        body = body.strip_suffix(']').unwrap_or(body).trim_end();
+        let open = match body.strip_suffix(", ...") {
+            Some(b) => {
+                body = b;
+                true
+            },
+            None => false,
+        };
+        let mut tape = String::new();
+        for run in body.split(',').map(str::trim).filter(|_| !body.is_empty()) {
+            match run.parse::<usize>() {
+                Ok(g) if g > Self::MAX_RUN => return Err( ParseError::Run(g) ),
+                Ok(g) => {
+                    tape.push_str( &"1".repeat(g) );
+                    tape.push('0');
+                },
+                Err(_) => return Err( ParseError::Symbol( run.to_string() ) ),
+            }
+        }
+        if tape.is_empty() && !open {
+            return Err( ParseError::Empty );
+        }
+        if open {
+            tape.pop();
+        }
+        Self::enclose( &tape, open ).ok_or( ParseError::Empty )
+    }
+
+    pub fn parse_pretty( input: &str ) -> Result<(Frac,Frac), ParseError> {
+    // Parser for the symbols of pretty, e.g. "△○△○□". Only the end symbol '□' makes the number
+    // fully determined, without it the expansion is taken as incomplete and an interval is produced.
+        let mut tape = String::new();
+        let mut open = true;
+        for c in input.trim().chars() {
+            if !open {
+                return Err( ParseError::Symbol( c.to_string() ) );
+            }
+            match c {
+                '○' => tape.push('0'),
+                '△' => tape.push('1'),
+                '□' => open = false,
+                _ => return Err( ParseError::Symbol( c.to_string() ) ),
+            }
+        }
+        if tape.is_empty() {
+            return Err( ParseError::Empty );
+        }
+        Self::enclose( &tape, open ).ok_or( ParseError::Empty )
+    }
 }
 
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParseError {
+    Empty,          // no digits (or runs) at all
+    Symbol(String), // a run length, or a pretty symbol, not recognised (or out of place)
+    Run(usize),     // a run longer than MAX_RUN
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ParseError::Empty => write!( f, "Empty k-log" ),
+            ParseError::Symbol(s) => write!( f, "Unrecognized '{}'", s ),
+            ParseError::Run(g) => write!( f, "Run length {} is over {}", g, KLog::<Frac>::MAX_RUN ),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
 impl KLogOps for KFrac {
     fn iota( &mut self) {
         self.frac1.num -= &self.frac1.den;
@@ -126,34 +284,37 @@ impl KLogOps for KFrac {
     fn gcd_div(&mut self) {
         self.gcd_div();
     }
-    // Direct computation of the shorthand exapnsion eliminates all the powers of two in one go
-    // but it requires the precomputation of s = log_2(x), which requires its own loop anyway...
-    /*fn theta_iota( &mut self, mut s: u32 ) {
-        let mut z = std::cmp::min( self.frac1.num.find_one(0).unwrap(), self.frac2.num.find_one(0).unwrap() );
-        z = std::cmp::min( z, s );
-        if z > 0 {
-            self.frac1.num >>= z;
-            self.frac2.num >>= z;
-        }
-        s -= z;
-        if s > 0 {
-            self.frac1.den <<= s;
-            self.frac2.den <<= s;
-        }
-        self.iota();
-    }*/
+    fn pow2_div(&mut self) {
+        self.pow2_div();
+    }
+    fn bits(&self) -> u64 {
+        self.bits()
+    }
+    fn theta_run( &mut self, s: u32 ) {
+        // Direct computation of the shorthand expansion eliminates all the powers of two in one go,
+        // with the common ones taken from the numerators
+        let z1 = self.frac1.num.find_one(0).unwrap_or(s);
+        let z = self.frac2.num.find_one(0).unwrap_or(s).min(z1).min(s);
+        self.frac1.num >>= z;
+        self.frac2.num >>= z;
+        self.frac1.den <<= s - z;
+        self.frac2.den <<= s - z;
+    }
 }
 
 impl KLog<KFrac> {
     pub fn red1( &mut self, ratio: usize) -> bool {
         // This is the unchecked version, assuming propagation eventually helps
         // Infinite loop for numbers astronomically close to 1 -> [1-ε, 1+ε]
-        let (s1, s2) = self.remainder.atleast2();
+        let ((s1, s2), (t1, t2)) = {
+            let (e1, e2) = self.remainder.enclosure();
+            ( (e1.atleast2(), e2.atleast2()), (e1.atleast1(), e2.atleast1()) )
+        };
         if s1 && s2 {
             self.remainder.theta();
             self.tape.push(1);
             true
-        } else if !s1 && !s2 && self.remainder.atleast1() == (true, true) {
+        } else if !s1 && !s2 && t1 && t2 {
             self.remainder.iota();
             self.tape.push(0);
             true
@@ -164,37 +325,69 @@ impl KLog<KFrac> {
             false
         }
     }
+    pub fn red_run( &mut self, ratio: usize, max: usize ) -> usize {
+        // Fused version of red1: the run of '1's on which both convergents agree is read off the bit
+        // lengths and applied in one shift, followed by the '0' if they also agree on it.
+        // At most 'max' digits are emitted, their number is returned (0 after propagation). Whatever
+        // the leading words decide goes first, in one update of the convergents.
+        let k = match self.red_lehmer( max ) {
+            0 => self.digits( max ),
+            k => k,
+        };
+        if k == 0 {
+            for _ in 0..ratio {
+                self.remainder.prop();
+            }
+        }
+        k
+    }
     // irrationals require the target number of (new) digits to eventually stop
-    pub fn red( &mut self, mut new_digits: usize, ratio: Option<f64>, verbose: bool ) {
-        let limit = self.tape.len() + new_digits;
+    pub fn red( &mut self, new_digits: usize, ratio: Option<f64>, verbose: bool ) {
+        if let Err(e) = self.try_red( new_digits, ratio, verbose, &Budget::new() ) {
+            println!("\u{274c} KLog<KFrac>: {}", e);
+        }
+    }
+    pub fn try_red( &mut self, mut new_digits: usize, ratio: Option<f64>, verbose: bool, budget: &Budget ) -> Result<(), RedError> {
+        // As red, but gives up (keeping all the digits so far) when the budget runs out
+        let limit = self.len() + new_digits;
         if self.remainder.atleast1() == (false, false) {
-            println!("\u{274c} KLog<KFrac>: Can't reduce numbers (KFrac) less than 1!");
-            return;
+            return Err( RedError::BelowOne );
         }
         if !self.remainder.finite() {
-            println!("\u{274c} KLog<KFrac>: Both convergents are infinite!");
-            return;
+            return Err( RedError::Infinite );
         }
+        let mut meter = Meter::new( budget, self.remainder.n );
+        let start = Instant::now();
         // pro-phase: extract what's freely available (but not more!)
-        while new_digits > 0 && self.red1(0) {
-            new_digits -= 1;
+        loop {
+            let k = self.red_run( 0, new_digits );
+            if k == 0 {
+                break;
+            }
+            new_digits -= k;
         }
+        self.stream()?;
         // pre-phase: assuming 1 term per 1 convergent by default 
         let rx = ratio.unwrap_or(1.0);
         for _ in 0..((rx * new_digits as f64).round() as usize) {
             self.remainder.prop();
+            meter.progress( self.remainder.n ); // planned, not a stall
+            meter.check( self.remainder.n, self.remainder.bits() )?;
         }
-        self.remainder.gcd_div();
+        self.norm.chunk( &mut self.remainder );
+        report( &self.observer, start, Stage::Prop, self.len(), self.remainder.n, self.remainder.bits() );
         let ry = rx.round().max(1.0) as usize ;
-        if verbose {
-            while self.tape.len() < limit {
-                self.red1( ry );
-                println!("{}", self );
-            }
-        } else {
-            while self.tape.len() < limit {
-                self.red1( ry );
+        while self.len() < limit {
+            if self.red_run( ry, limit - self.len() ) > 0 {
+                meter.progress( self.remainder.n );
             }
+            self.norm.step( &mut self.remainder );
+            if verbose { println!("{}", self ); }
+            self.stream()?;
+            report( &self.observer, start, Stage::Red, self.len(), self.remainder.n, self.remainder.bits() );
+            meter.check( self.remainder.n, self.remainder.bits() )?;
         }
+        report( &self.observer, start, Stage::Done, self.len(), self.remainder.n, self.remainder.bits() );
+        Ok(())
     }
 }
@@ -2,27 +2,53 @@
 // usable as the remainder of a KLog
 
 use std::fmt::Display;
+use crate::int::{self, Integer};
+use crate::fractions::Frac;
+use crate::norm::{Norm, Normaliser};
+use crate::progress::{Observer, Watch};
+use crate::budget::RedError;
+use crate::stream::{self, Sink};
+use std::sync::Arc;
 
 pub trait KLogOps {
     // The operation corresponding to '0': x -> 1/(x-1)
     fn iota(&mut self);
     // The operation corresponding to '1': x -> x/2
     fn theta(&mut self);
-    // reducing the remaining fraction(s) via gcd -- costly! Currently only used at creation.
+    // reducing the remaining fraction(s) via gcd -- costly! Applied according to the KLog's Normaliser
     fn gcd_div(&mut self);
-    //fn theta_iota(&mut self, &mut usize); // composition of the above
+    // the cheap part of gcd_div: only the common powers of two
+    fn pow2_div(&mut self) {}
+    // size of the remaining fraction(s), for the normalisation policies and their statistics
+    fn bits(&self) -> u64 {
+        0
+    }
+    // A run of s '1's: x -> x/2^s, implementations should apply the shifts in one go
+    fn theta_run(&mut self, s: u32) {
+        for _ in 0..s {
+            self.theta();
+        }
+    }
 }
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct KLog<T: KLogOps> {
+    #[cfg_attr(feature = "serde", serde(with = "crate::ser::bits"))]
     pub tape: Vec<u8>,
     pub remainder: T,
     pub done: bool,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub norm: Normaliser,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub observer: Watch,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub sink: Option<Sink>,
 }
 
 impl<T> Display for KLog<T>
 where T: KLogOps + Display {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        if self.tape.len() == 0 {
+        if self.tape.is_empty() {
             return write!( f, "_ ({})", self.remainder );
         }
         let tape_s = self.tape_str();
@@ -36,6 +62,48 @@ where T: KLogOps + Display {
 }
 
 impl<T: KLogOps> KLog<T> {
+    pub fn with_observer( mut self, o: Arc<dyn Observer> ) -> Self {
+        self.observer = Some(o);
+        self
+    }
+    pub fn with_sink( mut self, s: Sink ) -> Self {
+        self.sink = Some(s);
+        self
+    }
+    pub fn with_norm( mut self, policy: Norm ) -> Self {
+    // the normalisation policy, which also decides on the normalisation at creation as long as no
+    // reduction ran yet
+        self.norm.policy = policy;
+        self
+    }
+    pub fn len( &self ) -> usize {
+    // digits so far, with those already streamed out of the tape window
+        self.tape.len() + self.sink.as_ref().map_or( 0, |s| s.dropped() )
+    }
+    pub fn is_empty( &self ) -> bool {
+        self.len() == 0
+    }
+    pub fn trimmed( &self ) -> bool {
+    // whether the sink dropped digits from the front of the tape, after which pretty and gen_tape only
+    // show the window, the try_ versions and gen_str give None, prefixes none, and interval and lookup
+    // need the sink map
+        self.len() != self.tape.len()
+    }
+    pub(crate) fn stream( &mut self ) -> Result<(), RedError> {
+    // hands the new digits to the sink, if any
+        match self.sink.as_mut() {
+            Some(s) => s.digits( &mut self.tape ).map_err( |e| RedError::Io( e.kind() ) ),
+            None => Ok(()),
+        }
+    }
+    pub fn close_sink( &mut self ) -> std::io::Result<()> {
+    // streams what's left and closes the sink (which stays attached, so len() keeps counting)
+        if let Some(s) = self.sink.as_mut() {
+            s.digits( &mut self.tape )?;
+            if self.done { s.finish()? } else { s.close()? };
+        }
+        Ok(())
+    }
     pub fn tape_str( &self ) -> String {
     // k-log digits as String
         self.tape.iter().map( |q| q.to_string() ).collect()
@@ -57,6 +125,11 @@ impl<T: KLogOps> KLog<T> {
         prettape
     }
 
+    pub fn try_pretty( &self ) -> Option<String> {
+    // pretty, but None for a trimmed tape, which it would not show in full
+        (!self.trimmed()).then( || self.pretty() )
+    }
+
     pub fn gen_tape( &self ) -> Vec<u128> {
     // k-log in shorthand notation
         let mut gen_tape = Vec::<u128>::new();
@@ -74,11 +147,122 @@ impl<T: KLogOps> KLog<T> {
         }
         gen_tape
     }
+
+    pub fn try_gen_tape( &self ) -> Option<Vec<u128>> {
+    // gen_tape, but None for a trimmed tape, whose first run may be cut short
+        (!self.trimmed()).then( || self.gen_tape() )
+    }
+
+    pub fn gen_str( &self ) -> Option<String> {
+    // The shorthand as text, as read back by parse_gen: an unfinished tape ends with its open run
+    // (possibly of no '1's) and "...", e.g. "[3, 0, 2, ...]"
+        let mut runs = self.try_gen_tape()?;
+        if self.done {
+            return Some( format!( "{:?}", runs ) );
+        }
+        if self.tape.last() != Some(&1) {
+            runs.push(0);
+        }
+        let runs: Vec<String> = runs.iter().map( |g| g.to_string() ).collect();
+        Some( format!( "[{}, ...]", runs.join(", ") ) )
+    }
+
+    pub fn prefixes( &self ) -> KLogPrefixes<'_> {
+    // The intervals pinned down by each prefix of the tape, starting from the empty one; none for a
+    // trimmed tape, as the window alone pins nothing down
+        KLogPrefixes {
+            tape: &self.tape,
+            done: self.done,
+            pos: if self.trimmed() { self.tape.len() + 1 } else { 0 },
+            ones: 0,
+            mat: ((Integer::from(1), Integer::from(0)), (Integer::from(0), Integer::from(1))),
+        }
+    }
+
+    pub fn interval( &self ) -> Option<(Frac, Frac)> {
+    // The values consistent with the tape (just the value, when done), from its Mobius map applied to
+    // the remainders in [1, inf]. Once the sink trimmed the tape the map of the dropped digits is
+    // needed as well, which it only keeps with_map, otherwise None.
+        let mut m = stream::mobius( &self.tape, stream::digit_map );
+        if self.trimmed() {
+            let d = self.sink.as_ref()?.map()?;
+            m = stream::compose( d, &m );
+        }
+        let [a, b, c, d] = m;
+        let mut y1 = Frac::from_int( a.clone(), c.clone() );
+        y1.gcd_div();
+        if self.done {
+            return Some( (y1.clone(), y1) );
+        }
+        let mut y2 = Frac::from_int( a + b, c + d );
+        y2.gcd_div();
+        Some( if y1 <= y2 { (y1, y2) } else { (y2, y1) } )
+    }
+}
+
+pub struct KLogPrefix {
+    pub len: usize,
+    pub lo: Frac,
+    pub hi: Frac,
+    pub width_bits: f64, // log2 of hi-lo, +inf at first and -inf for a finished expansion
+    pub simplest: Frac,
+}
+
+pub struct KLogPrefixes<'a> {
+    tape: &'a [u8],
+    done: bool,
+    pos: usize,
+    ones: u64,
+    // The prefix as a Mobius map x = (a r + b)/(c r + d) of the remainder r in [1, inf]
+    mat: ((Integer, Integer), (Integer, Integer)),
+}
+
+impl Iterator for KLogPrefixes<'_> {
+    type Item = KLogPrefix;
+
+    fn next( &mut self ) -> Option<KLogPrefix> {
+        if self.pos > self.tape.len() {
+            return None;
+        }
+        let ((a, b), (c, d)) = &self.mat;
+        let mut y1 = Frac::from_int( a.clone(), c.clone() );
+        y1.gcd_div();
+        let exact = self.done && self.pos == self.tape.len();
+        let (lo, hi, width_bits) = if exact {
+            (y1.clone(), y1, f64::NEG_INFINITY)
+        } else {
+            let mut y2 = Frac::from_int( Integer::from(a + b), Integer::from(c + d) );
+            y2.gcd_div();
+            // |ad - bc| = 2^ones, so the width is 2^ones/(c(c+d))
+            let w = self.ones as f64 - int::log2( c ) - int::log2( &Integer::from(c + d) );
+            if Integer::from(&y1.num * &y2.den) <= Integer::from(&y2.num * &y1.den) {
+                (y1, y2, w)
+            } else {
+                (y2, y1, w)
+            }
+        };
+        let simplest = Frac::simplest( &lo, &hi );
+        let item = KLogPrefix { len: self.pos, lo, hi, width_bits, simplest };
+        if let Some(&s) = self.tape.get(self.pos) {
+            let ((a, b), (c, d)) = &mut self.mat;
+            if s == 1 {
+                *a <<= 1;
+                *c <<= 1;
+                self.ones += 1;
+            } else {
+                *b += &*a;
+                *d += &*c;
+                std::mem::swap( a, b );
+                std::mem::swap( c, d );
+            }
+        }
+        self.pos += 1;
+        Some(item)
+    }
 }
 
 impl<T: KLogOps> From<T> for KLog<T> {
-    fn from( mut f: T ) -> Self {
-        f.gcd_div();
-        KLog { tape: Vec::new(), remainder: f, done: false }
+    fn from( f: T ) -> Self {
+        KLog { tape: Vec::new(), remainder: f, done: false, norm: Normaliser::created(), observer: None, sink: None }
     }
 }
@@ -0,0 +1,292 @@
+// Inverse symbolic lookup: which known numbers agree with the interval pinned down by a k-log or
+// continued fraction prefix? Candidates are the Mobius transforms (a x + b)/(c x + d) of the
+// math_consts (and any added constants) with small coefficients, and the roots of small integer
+// polynomials, rationals being those of degree 1. A match only means consistency with the interval, so
+// short prefixes match a lot; the matches come simplest first.
+
+use std::fmt::Display;
+use crate::int::Integer;
+use crate::fractions::{Frac, KFrac, RegFrac, RegFracOps};
+use crate::logs::{KLog, KLogOps};
+use crate::math_consts;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Candidate {
+    Mobius( String, [i128; 4] ), // (a x + b)/(c x + d) of the named constant x, with [a, b, c, d]
+    Algebraic( Vec<i128> ),      // a root of c_0 + c_1 x + ... + c_n x^n, from [c_0, ..., c_n]
+}
+
+#[derive(Clone, Debug)]
+pub struct Match {
+    pub candidate: Candidate,
+    pub complexity: u32, // sum of the absolute coefficients, plus the degree or 2 for a transform
+}
+
+fn linear( a: i128, b: i128, x: &str ) -> String {
+    // a x + b, without the ones and zeros
+    let ax = match a {
+        0 => String::new(),
+        1 => x.to_string(),
+        -1 => format!( "-{}", x ),
+        _ => format!( "{}{}", a, x ),
+    };
+    match (ax.is_empty(), b) {
+        (true, _) => b.to_string(),
+        (false, 0) => ax,
+        (false, b) if b < 0 => format!( "{} - {}", ax, -b ),
+        (false, b) => format!( "{} + {}", ax, b ),
+    }
+}
+
+impl Display for Candidate {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Candidate::Mobius( x, [a, b, c, d] ) => {
+                let paren = |s: String| if s.contains(' ') { format!( "({})", s ) } else { s };
+                match (c, d) {
+                    (0, 1) => write!( f, "{}", linear( *a, *b, x ) ),
+                    _ => write!( f, "{}/{}", paren( linear( *a, *b, x ) ), paren( linear( *c, *d, x ) ) ),
+                }
+            },
+            Candidate::Algebraic(c) if c.len() == 2 => {
+                if c[1] == 1 { write!( f, "{}", -c[0] ) } else { write!( f, "{}/{}", -c[0], c[1] ) }
+            },
+            Candidate::Algebraic(c) => {
+                write!( f, "root of " )?;
+                let mut first = true;
+                for (i, &ci) in c.iter().enumerate().rev().filter( |(_, &ci)| ci != 0 ) {
+                    let sign = if ci < 0 { "-" } else if first { "" } else { "+" };
+                    let m = ci.unsigned_abs();
+                    let coef = if m == 1 && i > 0 { String::new() } else { m.to_string() };
+                    let pow = match i { 0 => String::new(), 1 => "x".to_string(), _ => format!( "x^{}", i ) };
+                    if first { write!( f, "{}{}{}", sign, coef, pow )?; } else { write!( f, " {} {}{}", sign, coef, pow )?; }
+                    first = false;
+                }
+                Ok(())
+            },
+        }
+    }
+}
+
+impl Display for Match {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!( f, "{} [{}]", self.candidate, self.complexity )
+    }
+}
+
+fn gcd( a: i128, b: i128 ) -> i128 {
+    if b == 0 { a.abs() } else { gcd( b, a % b ) }
+}
+
+fn divides( q: &[i128], p: &[i128] ) -> bool {
+    // whether q divides p over the rationals, by pseudo-division
+    let k = q.len() - 1;
+    let mut r: Vec<Integer> = p.iter().map( |&c| Integer::from(c) ).collect();
+    for i in (k..r.len()).rev() {
+        let t = r[i].clone();
+        for c in r.iter_mut() {
+            *c *= q[k];
+        }
+        for (j, &qj) in q.iter().enumerate() {
+            r[i - k + j] -= Integer::from( &t * qj );
+        }
+    }
+    r.iter().all( |c| *c == 0 )
+}
+
+#[derive(Clone)]
+pub struct Lookup {
+    pub constants: Vec<(String, KFrac)>,
+    pub mobius: i128,       // bound on |a|, |b|, |c|, |d| of the transforms
+    pub degree: usize,      // of the polynomials
+    pub height: i128,       // bound on their coefficients
+    pub convergents: i128,  // how far a constant may be propagated to get below the interval width
+    pub max: usize,         // matches returned
+}
+
+impl Default for Lookup {
+    fn default() -> Self {
+        Lookup {
+            constants: math_consts::NAMES.iter().filter_map( |&c| Some(( c.to_string(), KFrac::by_name(c)? )) ).collect(),
+            mobius: 3,
+            degree: 3,
+            height: 6,
+            convergents: 1 << 16,
+            max: 20,
+        }
+    }
+}
+
+impl Lookup {
+    pub fn new() -> Self {
+        Lookup::default()
+    }
+    pub fn with_constant( mut self, name: &str, k: KFrac ) -> Self {
+        self.constants.push(( name.to_string(), k ));
+        self
+    }
+    pub fn with_mobius( mut self, h: i128 ) -> Self {
+        self.mobius = h;
+        self
+    }
+    pub fn with_degree( mut self, d: usize ) -> Self {
+        self.degree = d;
+        self
+    }
+    pub fn with_height( mut self, h: i128 ) -> Self {
+        self.height = h;
+        self
+    }
+    pub fn with_max( mut self, m: usize ) -> Self {
+        self.max = m;
+        self
+    }
+
+    pub fn search( &self, lo: &Frac, hi: &Frac ) -> Vec<Match> {
+        // The candidates consistent with the closed interval between lo and hi, simplest first.
+        // Polynomials must change sign over it (or vanish at an end), so their matches are certain.
+        // Constants are propagated until far narrower than the interval, and a transform matches if
+        // its image overlaps; transforms whose image is wider (near their pole) are left out.
+        let (mut lo, mut hi) = if lo <= hi { (lo.clone(), hi.clone()) } else { (hi.clone(), lo.clone()) };
+        if !lo.finite() || !hi.finite() {
+            return Vec::new();
+        }
+        lo.normalise();
+        hi.normalise();
+        let mut found = self.algebraic( &lo, &hi );
+        let width = &hi - &lo;
+        if width.num != 0 {
+            for (name, k) in self.constants.iter() {
+                found.extend( self.transforms( name, k, &lo, &hi, &width ) );
+            }
+        }
+        found.sort_by_key( |m| m.complexity );
+        found.truncate( self.max );
+        found
+    }
+
+    fn algebraic( &self, lo: &Frac, hi: &Frac ) -> Vec<Match> {
+        let h = self.height;
+        let powers = |x: &Frac| -> (Vec<Integer>, Vec<Integer>) {
+            let (mut n, mut d) = (vec![ Integer::from(1) ], vec![ Integer::from(1) ]);
+            for i in 0..self.degree {
+                n.push( Integer::from( &n[i] * &x.num ) );
+                d.push( Integer::from( &d[i] * &x.den ) );
+            }
+            (n, d)
+        };
+        let (ln, ld) = powers( lo );
+        let (hn, hd) = powers( hi );
+        // the sign of p at n/d with d > 0, from the homogeneous d^k p(n/d)
+        let value = |c: &[i128], n: &[Integer], d: &[Integer]| -> Integer {
+            let k = c.len() - 1;
+            c.iter().enumerate().filter( |(_, &ci)| ci != 0 )
+                .map( |(i, &ci)| Integer::from( &n[i] * &d[k - i] ) * ci ).sum()
+        };
+        let mut found: Vec<Match> = Vec::new();
+        for k in 1..=self.degree {
+            // all c_0..c_k in [-h, h] with c_k > 0, as an odometer
+            let mut c = vec![ -h; k + 1 ];
+            c[k] = 1;
+            loop {
+                if c.iter().fold( 0, |g, &x| gcd( g, x ) ) == 1 {
+                    let v1 = value( &c, &ln, &ld );
+                    let v2 = value( &c, &hn, &hd );
+                    let sign_change = v1 == 0 || v2 == 0 || ((v1 < 0) != (v2 < 0));
+                    // reducible ones are covered by their factors
+                    let known = || found.iter().any( |m| match &m.candidate {
+                        Candidate::Algebraic(q) => q.len() <= k && divides( q, &c ),
+                        _ => false,
+                    });
+                    if sign_change && !known() {
+                        let complexity = c.iter().map( |x| x.unsigned_abs() as u32 ).sum::<u32>() + k as u32;
+                        found.push( Match { candidate: Candidate::Algebraic( c.clone() ), complexity } );
+                    }
+                }
+                let Some(i) = (0..=k).find( |&i| c[i] < h ) else {
+                    break;
+                };
+                c[i] += 1;
+                for x in c[..i].iter_mut() {
+                    *x = -h;
+                }
+            }
+        }
+        found
+    }
+
+    fn transforms( &self, name: &str, k: &KFrac, lo: &Frac, hi: &Frac, width: &Frac ) -> Vec<Match> {
+        // the constant to well below the interval width, with margin for the slopes of the transforms
+        let margin = 24 + 4 * (128 - self.mobius.leading_zeros());
+        let mut k = k.clone();
+        let mut step = 16;
+        let (cl, ch) = loop {
+            let (e1, e2) = k.enclosure();
+            if e1.finite() && e2.finite() {
+                let mut w = (&*e1 - &*e2).abs();
+                w.num <<= margin;
+                if w <= *width {
+                    let (mut e1, mut e2) = (e1.into_owned(), e2.into_owned());
+                    e1.normalise();
+                    e2.normalise();
+                    break if e1 <= e2 { (e1, e2) } else { (e2, e1) };
+                }
+            }
+            if k.n > self.convergents {
+                return Vec::new();
+            }
+            k.prop_bs( step );
+            step *= 2;
+        };
+        let h = self.mobius;
+        let mut found = Vec::new();
+        for a in -h..=h {
+            for b in -h..=h {
+                for c in 0..=h {
+                    for d in -h..=h {
+                        // one of each class: c > 0, or c = 0 with d > 0, in lowest terms, non-degenerate
+                        if (c == 0 && d <= 0) || a * d == b * c || gcd( gcd( a, b ), gcd( c, d ) ) != 1 {
+                            continue;
+                        }
+                        let image = |x: &Frac| Frac::from_int(
+                            Integer::from( &x.num * a ) + Integer::from( &x.den * b ),
+                            Integer::from( &x.num * c ) + Integer::from( &x.den * d ) );
+                        let (y1, y2) = ( image( &cl ), image( &ch ) );
+                        if y1.den == 0 || y2.den == 0 || (y1.den < 0) != (y2.den < 0) {
+                            continue; // the pole is within reach
+                        }
+                        let (il, ih) = if y1 <= y2 { (y1, y2) } else { (y2, y1) };
+                        if &ih - &il > *width || ih < *lo || il > *hi {
+                            continue;
+                        }
+                        let complexity = (a.unsigned_abs() + b.unsigned_abs() + c.unsigned_abs() + d.unsigned_abs()) as u32 + 2;
+                        found.push( Match { candidate: Candidate::Mobius( name.to_string(), [a, b, c, d] ), complexity } );
+                    }
+                }
+            }
+        }
+        found
+    }
+}
+
+impl<T: KLogOps> KLog<T> {
+    pub fn lookup( &self, l: &Lookup ) -> Vec<Match> {
+        // candidates for the number whose k-log starts with the tape (or is the tape, when done),
+        // none once the sink has trimmed it without keeping its map
+        match self.interval() {
+            Some((lo, hi)) => l.search( &lo, &hi ),
+            None => Vec::new(),
+        }
+    }
+}
+
+impl<T: RegFracOps> RegFrac<T> {
+    pub fn lookup( &self, l: &Lookup ) -> Vec<Match> {
+        // candidates for the number whose continued fraction starts with the tape, or is the tape
+        // when the expansion is finished
+        match self.ends() {
+            Some((lo, hi)) => l.search( &lo, &hi ),
+            None => Vec::new(),
+        }
+    }
+}
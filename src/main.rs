@@ -4,6 +4,7 @@ use std::time::Instant;
 use k_log::math_consts;
 use k_log::{Frac, KFrac};
 use k_log::KLog;
+use k_log::{Format, Sink};
 
 fn main() {
     let eta0 = Instant::now();
@@ -16,25 +17,37 @@ fn main() {
     w.gcd_div();
     w.stats();
     println!("{}", w.frac2.float(64));
-    let mut x = KLog::from( w );
+    // the digits go to a file as they come, only the last 2^20 stay in memory
+    let sink = match Sink::create( "pi.klog", Format::Digits ) {
+        Ok(s) => s.with_tail( 1 << 20 ),
+        Err(e) => {
+            println!("\u{274c} Can't create pi.klog: {}", e);
+            return;
+        }
+    };
+    let mut x = KLog::from( w ).with_sink( sink );
     while x.red1(0) {}
     let mut n0 = x.remainder.n;
-    let mut g = n0 as f64 /x.tape.len() as f64;
-    println!("tape len {} initial ratio {:.4}", x.tape.len(), g);
+    let mut g = n0 as f64 /x.len() as f64;
+    println!("tape len {} initial ratio {:.4}", x.len(), g);
     let chunk = 10_000;
     for _ in 0..99 {
-        x.red( chunk.min(10_000_000-x.tape.len()), Some(g), false);
+        x.red( chunk.min(10_000_000-x.len()), Some(g), false);
         x.remainder.stats();
         g +=  (x.remainder.n-n0) as f64 /chunk as f64;
         g *= 0.5;
-        println!("tape len {} convergent #{} ratio {:.4}", x.tape.len(), x.remainder.n, g);
+        println!("tape len {} convergent #{} ratio {:.4}", x.len(), x.remainder.n, g);
         n0 = x.remainder.n;
-        if x.tape.len() == 10_000_000 {break;}
+        if x.len() == 10_000_000 {break;}
     }
     let dl = x.remainder.frac2.den.to_string_radix(10).len();
     let t2 = eta0.elapsed().as_millis();
-    println!("Klog has {} digits, convergent #{}, denominator len {}. {}ms", x.tape.len(), x.remainder.n, dl, t2 );
+    println!("Klog has {} digits, convergent #{}, denominator len {}. {}ms", x.len(), x.remainder.n, dl, t2 );
+    if let Err(e) = x.close_sink() {
+        println!("\u{274c} Writing pi.klog failed: {}", e);
+    }
     println!("{}", x );
+    // the runs of the window, the first one possibly cut short
     let gt = x.gen_tape();
-    println!("{:?}\n{} gigits, max: {:?}", &gt[99_900..100_010], gt.len(), gt.iter().max().unwrap() );
+    println!("{:?}\n{} gigits in the last {} digits, max: {:?}", &gt[gt.len()-110..], gt.len(), x.tape.len(), gt.iter().max().unwrap() );
 }
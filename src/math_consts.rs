@@ -104,3 +104,22 @@ pub fn zeta3_ab(n: I) -> I2 {
         ( -m*m*m, (2*n-1)*(17*n*(n-1)+5) )
     }
 }
+
+// All of the above by name (without the _ab), for the bindings
+pub const NAMES: [&str; 10] = ["sqrt2", "sqrt13", "gold", "e", "e2", "pi", "pi2", "log2", "zeta2", "zeta3"];
+
+pub fn by_name(name: &str) -> Option<fn(I) -> I2> {
+    match name {
+        "sqrt2" => Some(sqrt2_ab),
+        "sqrt13" => Some(sqrt13_ab),
+        "gold" => Some(gold_ab),
+        "e" => Some(e_ab),
+        "e2" => Some(e2_ab),
+        "pi" => Some(pi_ab),
+        "pi2" => Some(pi2_ab),
+        "log2" => Some(log2_ab),
+        "zeta2" => Some(zeta2_ab),
+        "zeta3" => Some(zeta3_ab),
+        _ => None,
+    }
+}
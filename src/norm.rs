@@ -0,0 +1,102 @@
+// Normalisation policies - when the reductions should bring the remaining fraction(s) to lowest
+// terms, since a full gcd is costly but unreduced fractions make every other step slower
+
+use crate::logs::KLogOps;
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Norm {
+    Never,       // leave the fractions as they come
+    Chunks,      // full gcd at creation and after each propagation phase (the old behaviour)
+    Every(usize),// full gcd every n reduction steps, and after propagation phases
+    Grown(u64),  // full gcd once the fractions grew by this many bits since the last one
+    Pow2,        // only strip the common powers of two, at every step
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct NormStats {
+    pub gcds: u64,       // full gcd reductions performed
+    pub pow2s: u64,      // power of two strips performed
+    pub bits_saved: u64, // total number of bits removed from the fractions by both
+    pub time: Duration,  // spent in them
+    pub bit_steps: u128, // the growth avoided: bits_saved summed over the reduction steps after, i.e.
+                         // the bits the steps would have carried along too, which is what they cost
+}
+
+#[derive(Clone, Debug)]
+pub struct Normaliser {
+    pub policy: Norm,
+    pub stats: NormStats,
+    steps: usize,
+    last_bits: u64,
+    pending: bool, // the normalisation at creation, left to the first step or chunk
+}
+
+impl Default for Normaliser {
+    fn default() -> Self {
+        Normaliser::new( Norm::Chunks )
+    }
+}
+
+impl Normaliser {
+    pub fn new( policy: Norm ) -> Self {
+        Normaliser { policy, stats: NormStats::default(), steps: 0, last_bits: 0, pending: false }
+    }
+
+    pub(crate) fn created() -> Self {
+        // For a KLog just made from its remainder: the creation gcd of the old code waits for the
+        // first reduction, so that a policy chosen after (KLog::with_norm) applies to it as well
+        Normaliser { pending: true, ..Normaliser::default() }
+    }
+
+    fn gcd<T: KLogOps>( &mut self, x: &mut T ) {
+        let (b0, t0) = ( x.bits(), Instant::now() );
+        x.gcd_div();
+        self.stats.time += t0.elapsed();
+        self.last_bits = x.bits();
+        self.stats.gcds += 1;
+        self.stats.bits_saved += b0 - self.last_bits;
+        self.steps = 0;
+    }
+
+    fn pow2<T: KLogOps>( &mut self, x: &mut T ) {
+        let (b0, t0) = ( x.bits(), Instant::now() );
+        x.pow2_div();
+        self.stats.time += t0.elapsed();
+        self.stats.pow2s += 1;
+        self.stats.bits_saved += b0 - x.bits();
+    }
+
+    pub fn chunk<T: KLogOps>( &mut self, x: &mut T ) {
+        // At creation and after bulk propagation, where the old code called gcd_div
+        self.pending = false;
+        match self.policy {
+            Norm::Never => {},
+            Norm::Pow2 => self.pow2( x ),
+            _ => self.gcd( x ),
+        }
+    }
+
+    pub fn step<T: KLogOps>( &mut self, x: &mut T ) {
+        // After each reduction step (digit, run or term)
+        if self.pending {
+            self.chunk( x );
+        }
+        self.stats.bit_steps += self.stats.bits_saved as u128;
+        match self.policy {
+            Norm::Never | Norm::Chunks => {},
+            Norm::Every(n) => {
+                self.steps += 1;
+                if self.steps >= n {
+                    self.gcd( x );
+                }
+            },
+            Norm::Grown(b) => {
+                if x.bits() > self.last_bits + b {
+                    self.gcd( x );
+                }
+            },
+            Norm::Pow2 => self.pow2( x ),
+        }
+    }
+}
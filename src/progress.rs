@@ -0,0 +1,48 @@
+// Progress reporting for long expansions: the reductions and binary splitting call an Observer
+// (when one is attached) instead of printing, so progress bars, logs or metrics can be driven from
+// outside the library.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Stage {
+    Prop,  // a block of propagation (pre-phase, or a chunk of red_adapt) has finished
+    Red,   // a reduction step
+    Split, // a block of prop_bs, 'tape' then counts the terms multiplied in so far and 'bits'
+           // is the size of the block
+    Done,  // the call is finished
+}
+
+#[derive(Clone, Debug)]
+pub struct Progress {
+    pub stage: Stage,
+    pub tape: usize,       // digits (or terms) so far
+    pub n: i128,           // convergent index of the remainder
+    pub bits: u64,         // size of the remainder
+    pub elapsed: Duration, // since the start of the call
+}
+
+pub trait Observer: Send + Sync {
+    // Called a lot (at every reduction step), so anything expensive should be throttled here
+    fn progress( &self, p: &Progress );
+}
+
+pub type Watch = Option<Arc<dyn Observer>>;
+
+pub(crate) fn report( w: &Watch, start: Instant, stage: Stage, tape: usize, n: i128, bits: u64 ) {
+    if let Some(o) = w {
+        o.progress( &Progress { stage, tape, n, bits, elapsed: start.elapsed() } );
+    }
+}
+
+pub struct Print( pub usize );
+
+impl Observer for Print {
+    // The old println! reports, every so many digits (and at every block and at the end)
+    fn progress( &self, p: &Progress ) {
+        if p.stage != Stage::Red || (self.0 > 0 && p.tape.is_multiple_of( self.0 )) {
+            println!( "tape len {} convergent #{} bits {} ({:?}, {}ms)", p.tape, p.n, p.bits, p.stage, p.elapsed.as_millis() );
+        }
+    }
+}
@@ -0,0 +1,463 @@
+// Python extension module (feature "python"), for interactive exploration, e.g. in Jupyter:
+//
+//     import k_log
+//     x = k_log.KLog(k_log.KFrac.constant("pi"))
+//     x.red(100)
+//     x.tape, x.gen_tape(), x.pretty()
+//
+// Rationals come and go as fractions.Fraction (ints, strings and Frac are accepted too), big
+// integers as Python ints, and k-log tapes as bytes of 0s and 1s.
+#![allow(clippy::useless_conversion)] // in the code generated by the pyo3 macros
+
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyDict, PyString};
+use rug::Integer;
+use std::cell::RefCell;
+use std::panic::{AssertUnwindSafe, catch_unwind, resume_unwind};
+use std::str::FromStr;
+
+use crate::{Budget, Frac, Gen, KFrac, KLog, RegFrac, Series, math_consts, qd};
+
+fn int_of( x: &Bound<'_, PyAny> ) -> PyResult<Integer> {
+    let s = x.str()?;
+    Integer::from_str( s.to_str()? ).map_err( |e| PyValueError::new_err( e.to_string() ) )
+}
+
+fn py_int( py: Python<'_>, x: &Integer ) -> PyResult<PyObject> {
+    Ok( py.import_bound("builtins")?.getattr("int")?.call1( (x.to_string(),) )?.unbind() )
+}
+
+fn frac_of( x: &Bound<'_, PyAny> ) -> PyResult<Frac> {
+    // Frac, Fraction, int, float or a decimal string
+    if let Ok(f) = x.downcast::<PyFrac>() {
+        return Ok( f.borrow().inner.clone() );
+    }
+    if let Ok(s) = x.downcast::<PyString>() {
+        return s.to_str()?.parse::<Frac>().map_err( |e| PyValueError::new_err( e.to_string() ) );
+    }
+    if let Ok(v) = x.extract::<f64>() {
+        if !x.hasattr("numerator")? {
+            return Frac::try_from(v).map_err( |e| PyValueError::new_err( e.to_string() ) );
+        }
+    }
+    let num = int_of( &x.getattr("numerator")? )?;
+    let den = int_of( &x.getattr("denominator")? )?;
+    Ok( Frac::from_int( num, den ) )
+}
+
+fn py_frac( py: Python<'_>, x: &Frac ) -> PyResult<PyObject> {
+    // fractions.Fraction, or +-inf for 1/0
+    if !x.finite() {
+        let inf = if x.num < 0 { f64::NEG_INFINITY } else { f64::INFINITY };
+        return Ok( inf.into_py(py) );
+    }
+    let fraction = py.import_bound("fractions")?.getattr("Fraction")?;
+    Ok( fraction.call1( (py_int(py, &x.num)?, py_int(py, &x.den)?) )?.unbind() )
+}
+
+fn red_err( e: crate::RedError ) -> PyErr {
+    PyRuntimeError::new_err( e.to_string() )
+}
+
+thread_local! {
+    // The exception of a Python callback (generator or series term), left here while unwinding out
+    // of the computation it broke off, for the calling method to raise
+    static FAILURE: RefCell<Option<PyErr>> = const { RefCell::new(None) };
+}
+
+fn fail( e: PyErr ) -> ! {
+    FAILURE.with( |f| *f.borrow_mut() = Some(e) );
+    resume_unwind( Box::new("a Python callback raised") )
+}
+
+fn raised<T>( f: impl FnOnce() -> T ) -> PyResult<T> {
+    // f, with the exception of a failed callback raised again (other panics go on)
+    catch_unwind( AssertUnwindSafe(f) ).or_else( |p| match FAILURE.with( |f| f.borrow_mut().take() ) {
+        Some(e) => Err(e),
+        None => resume_unwind(p),
+    })
+}
+
+#[pyclass(name = "Frac")]
+#[derive(Clone)]
+pub struct PyFrac {
+    inner: Frac,
+}
+
+#[pymethods]
+impl PyFrac {
+    #[new]
+    #[pyo3(signature = (x, den = None))]
+    fn new( x: &Bound<'_, PyAny>, den: Option<&Bound<'_, PyAny>> ) -> PyResult<Self> {
+        let inner = match den {
+            Some(d) => Frac::from_int( int_of(x)?, int_of(d)? ),
+            None => frac_of(x)?,
+        };
+        Ok( PyFrac { inner } )
+    }
+    #[getter]
+    fn numerator( &self, py: Python<'_> ) -> PyResult<PyObject> {
+        py_int( py, &self.inner.num )
+    }
+    #[getter]
+    fn denominator( &self, py: Python<'_> ) -> PyResult<PyObject> {
+        py_int( py, &self.inner.den )
+    }
+    fn fraction( &self, py: Python<'_> ) -> PyResult<PyObject> {
+        py_frac( py, &self.inner )
+    }
+    fn __float__( &self ) -> f64 {
+        self.inner.float(64).to_f64()
+    }
+    fn __repr__( &self ) -> String {
+        format!( "Frac({})", self.inner )
+    }
+    fn __add__( &self, o: &Bound<'_, PyAny> ) -> PyResult<Self> {
+        Ok( PyFrac { inner: &self.inner + frac_of(o)? } )
+    }
+    fn __sub__( &self, o: &Bound<'_, PyAny> ) -> PyResult<Self> {
+        Ok( PyFrac { inner: &self.inner - frac_of(o)? } )
+    }
+    fn __mul__( &self, o: &Bound<'_, PyAny> ) -> PyResult<Self> {
+        Ok( PyFrac { inner: &self.inner * frac_of(o)? } )
+    }
+    fn __truediv__( &self, o: &Bound<'_, PyAny> ) -> PyResult<Self> {
+        Ok( PyFrac { inner: &self.inner / frac_of(o)? } )
+    }
+    fn __neg__( &self ) -> Self {
+        PyFrac { inner: -self.inner.clone() }
+    }
+    fn __richcmp__( &self, o: &Bound<'_, PyAny>, op: pyo3::basic::CompareOp ) -> PyResult<bool> {
+        Ok( op.matches( self.inner.cmp( &frac_of(o)? ) ) )
+    }
+}
+
+#[pyclass(name = "KFrac")]
+pub struct PyKFrac {
+    inner: KFrac,
+}
+
+#[pymethods]
+impl PyKFrac {
+    #[new]
+    fn new( ab: PyObject ) -> PyResult<Self> {
+        // from a Python callable n -> (a_n, b_n), whose exceptions come out of the method calling it
+        let gen = Gen::new( move |n| Python::with_gil( |py| {
+            let r = ab.call1( py, (n,) ).and_then( |r| {
+                let (a, b): (Bound<'_, PyAny>, Bound<'_, PyAny>) = r.extract(py)?;
+                Ok( (int_of(&a)?, int_of(&b)?) )
+            });
+            r.unwrap_or_else( |e| fail(e) )
+        }));
+        raised( || PyKFrac { inner: KFrac::from_gen(gen) } )
+    }
+    #[staticmethod]
+    fn constant( name: &str ) -> PyResult<Self> {
+        match KFrac::by_name(name) {
+            Some(k) => Ok( PyKFrac { inner: k } ),
+            None => Err( PyValueError::new_err( format!("Unknown constant '{}', try one of {:?}", name, math_consts::NAMES) ) ),
+        }
+    }
+    #[staticmethod]
+    fn constants() -> Vec<&'static str> {
+        math_consts::NAMES.to_vec()
+    }
+    #[getter]
+    fn n( &self ) -> i128 {
+        self.inner.n
+    }
+    #[pyo3(signature = (count = 1))]
+    fn prop( &mut self, count: i128 ) -> PyResult<()> {
+        raised( || {
+            if count == 1 {
+                self.inner.prop();
+            } else if count > 1 {
+                self.inner.prop_bs(count);
+            }
+        })
+    }
+    fn convergents( &self, py: Python<'_> ) -> PyResult<(PyObject, PyObject)> {
+        // the last two, as (frac1, frac2)
+        Ok( (py_frac(py, &self.inner.frac1)?, py_frac(py, &self.inner.frac2)?) )
+    }
+    fn modified( &self, py: Python<'_> ) -> PyResult<PyObject> {
+        py_frac( py, &raised( || self.inner.modified() )? )
+    }
+    #[pyo3(signature = (bits, convergents = None, seconds = None))]
+    fn evaluate( &mut self, py: Python<'_>, bits: u32, convergents: Option<i128>, seconds: Option<f64> ) -> PyResult<(PyObject, f64)> {
+        // the correctly rounded value (exactly, as a Fraction) and its error radius, giving up past
+        // the given convergent or time
+        let mut budget = Budget::new();
+        if let Some(n) = convergents {
+            budget = budget.with_convergents(n);
+        }
+        if let Some(t) = seconds {
+            let t = std::time::Duration::try_from_secs_f64(t).map_err( |e| PyValueError::new_err( e.to_string() ) )?;
+            budget = budget.with_time(t);
+        }
+        let (v, r) = raised( || self.inner.evaluate( bits, &budget ) )?.map_err( red_err )?;
+        let v = Frac::try_from(&v).map_err( |e| PyValueError::new_err( e.to_string() ) )?;
+        Ok( (py_frac(py, &v)?, r.to_f64()) )
+    }
+    fn validate<'py>( &self, py: Python<'py>, terms: usize ) -> PyResult<Bound<'py, PyDict>> {
+        let v = raised( || self.inner.gen().validate(terms) )?;
+        let d = PyDict::new_bound(py);
+        d.set_item( "terms", v.terms )?;
+        d.set_item( "pringsheim", v.pringsheim )?;
+        d.set_item( "worpitzky", v.worpitzky )?;
+        d.set_item( "positive", v.positive )?;
+        d.set_item( "bracketing", v.bracketing )?;
+        d.set_item( "shrinking", v.shrinking )?;
+        d.set_item( "rate", v.rate )?;
+        d.set_item( "ok", v.ok() )?;
+        Ok(d)
+    }
+    fn __repr__( &self ) -> String {
+        format!( "KFrac(#{} {})", self.inner.n, self.inner )
+    }
+}
+
+enum AnyKLog {
+    Frac(KLog<Frac>),
+    KFrac(KLog<KFrac>),
+}
+
+#[pyclass(name = "KLog")]
+pub struct PyKLog {
+    inner: AnyKLog,
+}
+
+#[pymethods]
+impl PyKLog {
+    #[new]
+    fn new( x: &Bound<'_, PyAny> ) -> PyResult<Self> {
+        // a KFrac (copied), or anything taken by Frac
+        let inner = match x.downcast::<PyKFrac>() {
+            Ok(k) => AnyKLog::KFrac( KLog::from( k.borrow().inner.clone() ) ),
+            Err(_) => AnyKLog::Frac( KLog::from( frac_of(x)? ) ),
+        };
+        Ok( PyKLog { inner } )
+    }
+    #[pyo3(signature = (digits = None))]
+    fn red( &mut self, digits: Option<usize> ) -> PyResult<()> {
+        // up to 'digits' new digits, all of them (rationals only) by default
+        match &mut self.inner {
+            AnyKLog::Frac(x) => {
+                if !x.remainder.atleast1() {
+                    return Err( PyValueError::new_err("Can't reduce numbers (Frac) less than 1!") );
+                }
+                let limit = digits.map_or( usize::MAX, |d| x.tape.len() + d );
+                while x.remainder.finite() && x.tape.len() < limit {
+                    x.red1();
+                }
+                x.done = !x.remainder.finite();
+                Ok(())
+            },
+            AnyKLog::KFrac(x) => {
+                let Some(d) = digits else {
+                    return Err( PyValueError::new_err("KLog of a KFrac needs the number of digits") );
+                };
+                raised( || x.try_red( d, None, false, &Budget::new() ) )?.map_err( red_err )
+            },
+        }
+    }
+    #[getter]
+    fn tape<'py>( &self, py: Python<'py> ) -> Bound<'py, PyBytes> {
+        match &self.inner {
+            AnyKLog::Frac(x) => PyBytes::new_bound( py, &x.tape ),
+            AnyKLog::KFrac(x) => PyBytes::new_bound( py, &x.tape ),
+        }
+    }
+    #[getter]
+    fn done( &self ) -> bool {
+        matches!( &self.inner, AnyKLog::Frac(x) if x.done )
+    }
+    fn gen_tape( &self ) -> Option<Vec<u128>> {
+        match &self.inner {
+            AnyKLog::Frac(x) => x.try_gen_tape(),
+            AnyKLog::KFrac(x) => x.try_gen_tape(),
+        }
+    }
+    fn pretty( &self ) -> Option<String> {
+        match &self.inner {
+            AnyKLog::Frac(x) => x.try_pretty(),
+            AnyKLog::KFrac(x) => x.try_pretty(),
+        }
+    }
+    fn interval( &self, py: Python<'_> ) -> PyResult<Option<(PyObject, PyObject)>> {
+        // the values consistent with the tape so far
+        let ends = match &self.inner {
+            AnyKLog::Frac(x) => x.interval(),
+            AnyKLog::KFrac(x) => x.interval(),
+        };
+        match ends {
+            Some((lo, hi)) => Ok( Some( (py_frac(py, &lo)?, py_frac(py, &hi)?) ) ),
+            None => Ok(None),
+        }
+    }
+    fn __len__( &self ) -> usize {
+        match &self.inner {
+            AnyKLog::Frac(x) => x.tape.len(),
+            AnyKLog::KFrac(x) => x.tape.len(),
+        }
+    }
+    fn __repr__( &self ) -> String {
+        match &self.inner {
+            AnyKLog::Frac(x) => format!( "KLog({})", x ),
+            AnyKLog::KFrac(x) => format!( "KLog({})", x ),
+        }
+    }
+}
+
+enum AnyRegFrac {
+    Frac(RegFrac<Frac>),
+    KFrac(RegFrac<KFrac>),
+}
+
+#[pyclass(name = "RegFrac")]
+pub struct PyRegFrac {
+    inner: AnyRegFrac,
+}
+
+#[pymethods]
+impl PyRegFrac {
+    #[new]
+    fn new( x: &Bound<'_, PyAny> ) -> PyResult<Self> {
+        let inner = match x.downcast::<PyKFrac>() {
+            Ok(k) => AnyRegFrac::KFrac( RegFrac::from( k.borrow().inner.clone() ) ),
+            Err(_) => AnyRegFrac::Frac( RegFrac::from( frac_of(x)? ) ),
+        };
+        Ok( PyRegFrac { inner } )
+    }
+    #[pyo3(signature = (terms = None))]
+    fn red( &mut self, terms: Option<usize> ) -> PyResult<()> {
+        match &mut self.inner {
+            AnyRegFrac::Frac(x) => {
+                let limit = terms.map_or( usize::MAX, |d| x.tape.len() + d );
+                while x.remainder.finite() && x.tape.len() < limit {
+                    x.red1();
+                }
+                Ok(())
+            },
+            AnyRegFrac::KFrac(x) => {
+                let Some(t) = terms else {
+                    return Err( PyValueError::new_err("RegFrac of a KFrac needs the number of terms") );
+                };
+                raised( || x.try_red( t, None, &Budget::new() ) )?.map_err( red_err )
+            },
+        }
+    }
+    #[getter]
+    fn terms( &self ) -> Vec<u128> {
+        match &self.inner {
+            AnyRegFrac::Frac(x) => x.tape.clone(),
+            AnyRegFrac::KFrac(x) => x.tape.clone(),
+        }
+    }
+    fn convergents( &self, py: Python<'_> ) -> PyResult<Vec<PyObject>> {
+        let c = match &self.inner {
+            AnyRegFrac::Frac(x) => x.convergents(),
+            AnyRegFrac::KFrac(x) => x.convergents(),
+        };
+        c.iter().map( |f| py_frac(py, f) ).collect()
+    }
+    fn best_approx( &self, py: Python<'_>, max_den: &Bound<'_, PyAny> ) -> PyResult<Option<(PyObject, PyObject)>> {
+        // the best approximation with a denominator up to max_den, and its error
+        let m = int_of(max_den)?;
+        let b = match &self.inner {
+            AnyRegFrac::Frac(x) => x.best_approx(&m),
+            AnyRegFrac::KFrac(x) => x.best_approx(&m),
+        };
+        match b {
+            Some((a, e)) => Ok( Some( (py_frac(py, &a)?, py_frac(py, &e)?) ) ),
+            None => Ok(None),
+        }
+    }
+    fn __len__( &self ) -> usize {
+        match &self.inner {
+            AnyRegFrac::Frac(x) => x.tape.len(),
+            AnyRegFrac::KFrac(x) => x.tape.len(),
+        }
+    }
+    fn __repr__( &self ) -> String {
+        match &self.inner {
+            AnyRegFrac::Frac(x) => format!( "RegFrac({})", x ),
+            AnyRegFrac::KFrac(x) => format!( "RegFrac({})", x ),
+        }
+    }
+}
+
+#[pyclass(name = "Series")]
+pub struct PySeries {
+    inner: Series,
+}
+
+#[pymethods]
+impl PySeries {
+    #[new]
+    fn new( term: PyObject ) -> PyResult<Self> {
+        // from a Python callable (n, previous term) -> term n, the error taken as the last term; its
+        // exceptions come out of the method calling it
+        let u = move |n: i128, x: &mut Frac| Python::with_gil( |py| {
+            let r = py_frac(py, x).and_then( |prev| frac_of( term.call1( py, (n, prev) )?.bind(py) ) );
+            *x = r.unwrap_or_else( |e| fail(e) );
+        });
+        raised( || PySeries { inner: Series::new( u, |_, t| t.clone() ) } )
+    }
+    #[pyo3(signature = (count = 1))]
+    fn prop( &mut self, count: usize ) -> PyResult<()> {
+        raised( || {
+            for _ in 0..count {
+                self.inner.prop();
+            }
+        })
+    }
+    #[getter]
+    fn n( &self ) -> i128 {
+        self.inner.n
+    }
+    #[getter]
+    fn sum( &self, py: Python<'_> ) -> PyResult<PyObject> {
+        let mut s = self.inner.sum.clone();
+        s.normalise();
+        py_frac( py, &s )
+    }
+    #[getter]
+    fn term( &self, py: Python<'_> ) -> PyResult<PyObject> {
+        py_frac( py, &self.inner.curr )
+    }
+    fn euler( &self ) -> PyResult<PyKFrac> {
+        raised( || PyKFrac { inner: KFrac::from_gen( self.inner.euler() ) } )
+    }
+    #[staticmethod]
+    fn power( c: Vec<Bound<'_, PyAny>>, z: &Bound<'_, PyAny> ) -> PyResult<PyKFrac> {
+        // the continued fraction of sum c_k z^k (by qd)
+        let c = c.iter().map( frac_of ).collect::<PyResult<Vec<_>>>()?;
+        match Series::from_power_series( &c, &frac_of(z)? ) {
+            Some(g) => Ok( PyKFrac { inner: KFrac::from_gen(g) } ),
+            None => Err( PyValueError::new_err("The qd algorithm broke down on these coefficients") ),
+        }
+    }
+    #[staticmethod]
+    fn qd( py: Python<'_>, c: Vec<Bound<'_, PyAny>> ) -> PyResult<Vec<PyObject>> {
+        let c = c.iter().map( frac_of ).collect::<PyResult<Vec<_>>>()?;
+        match qd(&c) {
+            Some(a) => a.iter().map( |f| py_frac(py, f) ).collect(),
+            None => Err( PyValueError::new_err("The qd algorithm broke down on these coefficients") ),
+        }
+    }
+    fn __repr__( &self ) -> String {
+        format!( "Series(#{} sum {})", self.inner.n, self.inner.sum )
+    }
+}
+
+#[pymodule]
+fn k_log( m: &Bound<'_, PyModule> ) -> PyResult<()> {
+    m.add_class::<PyFrac>()?;
+    m.add_class::<PyKFrac>()?;
+    m.add_class::<PyKLog>()?;
+    m.add_class::<PyRegFrac>()?;
+    m.add_class::<PySeries>()?;
+    Ok(())
+}
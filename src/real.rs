@@ -0,0 +1,562 @@
+// Real numbers as the reductions see them: anything that can give an interval enclosing its value
+// and narrow it down on request. Frac (exact), KFrac (convergents, or the image of its tail set) and
+// Series (partial sum and error bound) are Reals, and Mobius nodes (a x + b)/(c x + d) over any of
+// them are both Reals and remainders for KLog and RegFrac, so that any Real can be expanded with the
+// generic red_real.
+
+use std::borrow::Cow;
+use std::fmt::Display;
+use std::time::Instant;
+use crate::int::{Integer, IntOps};
+use crate::fractions::{Frac, KFrac, RegFrac, RegFracOps};
+use crate::logs::{KLog, KLogOps};
+use crate::series::Series;
+use crate::budget::{Budget, Meter, RedError};
+use crate::progress::{Stage, report};
+use crate::stream::Sink;
+
+pub trait Real {
+    // The two ends of an interval known to contain the value, in either order. An unbounded one
+    // is [1/0, -1/0], which makes all reductions wait for refinement.
+    fn enclosure( &self ) -> (Cow<'_, Frac>, Cow<'_, Frac>);
+    // Narrows the enclosure by one step of whatever the number is made of (a convergent, a term...)
+    fn refine( &mut self );
+    // k steps at once, for implementors with a faster way than one by one
+    fn refine_by( &mut self, k: usize ) {
+        for _ in 0..k {
+            self.refine();
+        }
+    }
+    // steps taken by refine so far, for budgets and progress reports
+    fn steps( &self ) -> i128 {
+        0
+    }
+    // whether the enclosure is a single point, which refine leaves alone
+    fn exact( &self ) -> bool {
+        false
+    }
+}
+
+impl Real for Frac {
+    fn enclosure( &self ) -> (Cow<'_, Frac>, Cow<'_, Frac>) {
+        ( Cow::Borrowed(self), Cow::Borrowed(self) )
+    }
+    fn refine( &mut self ) {}
+    fn exact( &self ) -> bool {
+        true
+    }
+}
+
+impl Real for KFrac {
+    fn enclosure( &self ) -> (Cow<'_, Frac>, Cow<'_, Frac>) {
+        KFrac::enclosure( self )
+    }
+    fn refine( &mut self ) {
+        self.prop();
+    }
+    fn refine_by( &mut self, k: usize ) {
+        self.prop_bs( k as i128 );
+    }
+    fn steps( &self ) -> i128 {
+        self.n
+    }
+}
+
+impl Real for Series {
+    fn enclosure( &self ) -> (Cow<'_, Frac>, Cow<'_, Frac>) {
+        // the partial sum, give or take the error bound
+        let e = self.error().abs();
+        ( Cow::Owned( &self.sum - &e ), Cow::Owned( &self.sum + &e ) )
+    }
+    fn refine( &mut self ) {
+        self.prop();
+    }
+    fn steps( &self ) -> i128 {
+        self.n
+    }
+}
+
+#[derive(Clone)]
+pub struct Mobius<R: Real> {
+    // (a x + b)/(c x + d) of the Real x, with m = [a, b, c, d]. The enclosure of x is kept (with
+    // positive denominators) and only taken again when x is refined, so x is only reached through
+    // the node.
+    pub m: [Integer; 4],
+    x: R,
+    ends: (Frac, Frac),
+}
+
+impl<R: Real> From<R> for Mobius<R> {
+    fn from( x: R ) -> Self {
+        Mobius::new( x, [1, 0, 0, 1] )
+    }
+}
+
+impl<R: Real> Mobius<R> {
+    pub fn new( x: R, m: [i128; 4] ) -> Self {
+        Mobius::with_int( x, m.map( Integer::from ) )
+    }
+    pub(crate) fn with_int( x: R, m: [Integer; 4] ) -> Self {
+        let ends = uprights( &x );
+        Mobius { m, x, ends }
+    }
+    pub fn inner( &self ) -> &R {
+        &self.x
+    }
+    pub fn image( &self, w: &Frac ) -> Frac {
+        let [a, b, c, d] = &self.m;
+        Frac::from_int( Integer::from( a * &w.num ) + b * &w.den, Integer::from( c * &w.num ) + d * &w.den )
+    }
+}
+
+// The digit and term maps act on the output of a node, i.e. on the rows (numerator, denominator) of
+// its coefficients: the first half of 'm' for the numerator and the second for the denominator.
+
+pub(crate) fn left( m: &mut [Integer], t: [Integer; 4] ) {
+    // the map (t0 y + t1)/(t2 y + t3) applied after the node
+    let k = m.len() / 2;
+    let [ta, tb, tc, td] = t;
+    for i in 0..k {
+        let (n, d) = (std::mem::take( &mut m[i] ), std::mem::take( &mut m[k + i] ));
+        m[i] = Integer::from( &ta * &n ) + &tb * &d;
+        m[k + i] = Integer::from( &tc * &n ) + &td * &d;
+    }
+}
+
+pub(crate) fn iota_rows( m: &mut [Integer] ) {
+    // 1/(y - 1): the denominator becomes the numerator, and numerator - denominator the denominator
+    let (num, den) = m.split_at_mut( m.len() / 2 );
+    for (n, d) in num.iter_mut().zip( den.iter_mut() ) {
+        *n -= &*d;
+        std::mem::swap( n, d );
+    }
+}
+
+pub(crate) fn theta_rows( m: &mut [Integer], s: u32 ) {
+    // y/2^s, the powers of two leaving the numerator first and the rest going to the denominator
+    let k = m.len() / 2;
+    let z = m[..k].iter().filter_map( |c| c.find_one(0) ).min().unwrap_or(s).min(s);
+    for c in m[..k].iter_mut() {
+        *c >>= z;
+    }
+    for c in m[k..].iter_mut() {
+        *c <<= s - z;
+    }
+}
+
+pub(crate) fn gcd_rows( m: &mut [Integer] ) {
+    let g = m.iter().fold( Integer::from(0), |g, c| g.gcd_of( c ) );
+    if g > 1 {
+        for c in m.iter_mut() {
+            *c /= &g;
+        }
+    }
+}
+
+pub(crate) fn pow2_rows( m: &mut [Integer] ) {
+    let z = m.iter().filter_map( |c| c.find_one(0) ).min().unwrap_or(0);
+    for c in m.iter_mut() {
+        *c >>= z;
+    }
+}
+
+pub(crate) fn bits_rows( m: &[Integer] ) -> u64 {
+    m.iter().map( |c| c.significant_bits() as u64 ).sum()
+}
+
+pub(crate) fn upright( f: Cow<'_, Frac> ) -> Frac {
+    // with a positive (or zero) denominator, without the gcd of normalise
+    let mut f = f.into_owned();
+    if f.den < 0 {
+        f.num = -std::mem::take( &mut f.num );
+        f.den = -std::mem::take( &mut f.den );
+    }
+    f
+}
+
+pub(crate) fn uprights<R: Real>( x: &R ) -> (Frac, Frac) {
+    let (l, u) = x.enclosure();
+    ( upright(l), upright(u) )
+}
+
+impl<R: Real> Real for Mobius<R> {
+    fn enclosure( &self ) -> (Cow<'_, Frac>, Cow<'_, Frac>) {
+        // the image of the enclosure of x, unbounded when the pole falls into it (as in KFrac::bounds)
+        let (l, u) = &self.ends;
+        let [_, _, c, d] = &self.m;
+        let d1 = Integer::from( c * &l.num ) + d * &l.den;
+        let d2 = Integer::from( c * &u.num ) + d * &u.den;
+        if !self.x.exact() && (d1 == 0 || d2 == 0 || (d1 < 0) != (d2 < 0)) {
+            return ( Cow::Owned( Frac::from_i128(1, 0) ), Cow::Owned( Frac::from_i128(-1, 0) ) );
+        }
+        ( Cow::Owned( self.image(l) ), Cow::Owned( self.image(u) ) )
+    }
+    fn refine( &mut self ) {
+        self.x.refine();
+        self.ends = uprights( &self.x );
+    }
+    fn refine_by( &mut self, k: usize ) {
+        self.x.refine_by( k );
+        self.ends = uprights( &self.x );
+    }
+    fn steps( &self ) -> i128 {
+        self.x.steps()
+    }
+    fn exact( &self ) -> bool {
+        self.x.exact()
+    }
+}
+
+impl<R: Real> KLogOps for Mobius<R> {
+    fn iota( &mut self ) {
+        iota_rows( &mut self.m );
+    }
+    fn theta( &mut self ) {
+        theta_rows( &mut self.m, 1 );
+    }
+    fn theta_run( &mut self, s: u32 ) {
+        theta_rows( &mut self.m, s );
+    }
+    fn gcd_div( &mut self ) {
+        gcd_rows( &mut self.m );
+    }
+    fn pow2_div( &mut self ) {
+        pow2_rows( &mut self.m );
+    }
+    fn bits( &self ) -> u64 {
+        bits_rows( &self.m )
+    }
+}
+
+impl<R: Real> RegFracOps for Mobius<R> {
+    fn term( &mut self, t: &Integer ) {
+        left( &mut self.m, [ Integer::from(0), Integer::from(1), Integer::from(1), Integer::from( -t ) ] );
+    }
+}
+
+impl<R: Real> Display for Mobius<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let (e1, e2) = self.enclosure();
+        write!( f, "[{}; {}]", e1, e2 )
+    }
+}
+
+struct Batch {
+    // Refinement steps per call: doubled whenever a refinement brought no digits (or terms), so the
+    // costly evaluations of the enclosure stay few for slowly converging Reals
+    size: usize,
+    fresh: bool, // digits since the last refinement
+}
+
+impl Batch {
+    const MAX: usize = 1 << 12;
+
+    fn new() -> Self {
+        Batch { size: 1, fresh: true }
+    }
+    fn next( &mut self ) -> usize {
+        if !self.fresh {
+            self.size = (2 * self.size).min( Batch::MAX );
+        }
+        self.fresh = false;
+        self.size
+    }
+}
+
+impl<R: Real + KLogOps> KLog<R> {
+    pub(crate) fn digits( &mut self, max: usize ) -> usize {
+        // The run of '1's on which both ends of the enclosure agree is read off the bit lengths and
+        // applied in one shift, followed by the '0' if they also agree on it. At most 'max' digits,
+        // their number is returned: 0 when the enclosure is too wide (or below 1).
+        let ((t1, t2), (s1, s2)) = {
+            let (e1, e2) = self.remainder.enclosure();
+            ( (e1.atleast1(), e2.atleast1()), (e1.log2_floor(), e2.log2_floor()) )
+        };
+        let s = match (s1, s2) {
+            (Some(s1), Some(s2)) => s1.min(s2),
+            (Some(s), None) | (None, Some(s)) => s,
+            (None, None) => return 0, // 1/0 has no digits, nor should iota be applied to it
+        };
+        let run = (s as usize).min(max);
+        if !(t1 && t2) || max == 0 || (run == 0 && s1 != s2) {
+            return 0;
+        }
+        self.remainder.theta_run( run as u32 );
+        self.tape.resize( self.tape.len() + run, 1 );
+        if run < max && s1 == s2 {
+            self.remainder.iota();
+            self.tape.push(0);
+            return run + 1;
+        }
+        run
+    }
+    pub fn red_real( &mut self, new_digits: usize ) {
+        if let Err(e) = self.try_red_real( new_digits, &Budget::new() ) {
+            println!("\u{274c} KLog: {}", e);
+        }
+    }
+    pub fn try_red_real( &mut self, new_digits: usize, budget: &Budget ) -> Result<(), RedError> {
+        // Reduction of any Real: digits while the enclosure allows, refinement otherwise. Exact
+        // values finish (done) at 1/0; for the others the target number of new digits stops it.
+        let limit = self.len() + new_digits;
+        let mut meter = Meter::new( budget, self.remainder.steps() );
+        let start = Instant::now();
+        let mut batch = Batch::new();
+        while self.len() < limit && !self.done {
+            let (infinite, below) = {
+                // below 1 as soon as the upper end of a finite enclosure is, exact or not
+                let (e1, e2) = self.remainder.enclosure();
+                ( !e1.finite() && !e2.finite(), e1.finite() && e2.finite() && !e1.atleast1() && !e2.atleast1() )
+            };
+            if infinite && self.remainder.exact() {
+                self.done = true;
+                break;
+            }
+            if !infinite && self.digits( limit - self.len() ) > 0 {
+                batch.fresh = true;
+                meter.progress( self.remainder.steps() );
+                self.norm.step( &mut self.remainder );
+            } else if below || self.remainder.exact() {
+                return Err( RedError::BelowOne );
+            } else {
+                self.remainder.refine_by( batch.next() );
+            }
+            self.stream()?;
+            report( &self.observer, start, Stage::Red, self.len(), self.remainder.steps(), self.remainder.bits() );
+            meter.check( self.remainder.steps(), self.remainder.bits() )?;
+        }
+        if self.done {
+            self.close_sink().map_err( |e| RedError::Io( e.kind() ) )?;
+        }
+        report( &self.observer, start, Stage::Done, self.len(), self.remainder.steps(), self.remainder.bits() );
+        Ok(())
+    }
+}
+
+impl<R: Real + RegFracOps + KLogOps> RegFrac<R> {
+    pub(crate) fn next_term( &mut self ) -> bool {
+        // the term on which both ends of the enclosure agree, if they do
+        let t = {
+            let (e1, e2) = self.remainder.enclosure();
+            if !e1.finite() || !e2.finite() {
+                return false;
+            }
+            let (Some(t), Some(t2)) = ( e1.floor(), e2.floor() ) else {
+                return false;
+            };
+            if t != t2 {
+                return false;
+            }
+            t
+        };
+        if let Some(c) = t.to_u128() {
+            self.tape.push(c);
+            self.remainder.term( &t );
+            true
+        } else {
+            println!("The next term does not fit in u128.");
+            panic!();
+        }
+    }
+    pub fn red_real( &mut self, new_terms: usize ) {
+        if let Err(e) = self.try_red_real( new_terms, &Budget::new() ) {
+            println!("\u{274c} RegFrac: {}", e);
+        }
+    }
+    pub fn try_red_real( &mut self, new_terms: usize, budget: &Budget ) -> Result<(), RedError> {
+        // As KLog::try_red_real, exact values end at 1/0
+        let limit = self.len() + new_terms;
+        let mut meter = Meter::new( budget, self.remainder.steps() );
+        let start = Instant::now();
+        let mut batch = Batch::new();
+        let mut finished = false;
+        while self.len() < limit {
+            if self.next_term() {
+                batch.fresh = true;
+                meter.progress( self.remainder.steps() );
+                self.norm.step( &mut self.remainder );
+            } else if self.remainder.exact() {
+                finished = true;
+                break;
+            } else {
+                self.remainder.refine_by( batch.next() );
+            }
+            self.stream()?;
+            report( &self.observer, start, Stage::Red, self.len(), self.remainder.steps(), self.remainder.bits() );
+            meter.check( self.remainder.steps(), self.remainder.bits() )?;
+        }
+        if finished {
+            self.close_sink().map_err( |e| RedError::Io( e.kind() ) )?;
+        }
+        report( &self.observer, start, Stage::Done, self.len(), self.remainder.steps(), self.remainder.bits() );
+        Ok(())
+    }
+}
+
+pub struct Pull<S> {
+    // A KLog or RegFrac read as a Real, e.g. to take roots of an expansion: the digits (or terms)
+    // taken so far are folded into m, the value being m(y) for the rest y >= 1, and refine has the
+    // source produce the next one. m = [a, b, c, d] as in Mobius. The sink of the source (if any) is
+    // held back while it reduces, so a tail window never drops digits not taken yet. A source whose
+    // tape was already trimmed starts from the map of the dropped part, which its sink must keep
+    // (Sink::with_map), otherwise try_from gives it back as Trimmed.
+    src: S,
+    m: [Integer; 4],
+    pos: usize,
+}
+
+pub struct Trimmed<S>( pub S );
+
+impl<S> Display for Trimmed<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!( f, "the tape of the source was trimmed by a sink without its map" )
+    }
+}
+
+impl<S> std::fmt::Debug for Trimmed<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!( f, "Trimmed" )
+    }
+}
+
+impl<S> std::error::Error for Trimmed<S> {}
+
+fn dropped_map( sink: Option<&Sink>, trimmed: bool ) -> Option<[Integer; 4]> {
+    // the map of what the sink dropped from the tape, the identity if nothing was
+    match sink.and_then( Sink::map ) {
+        Some(m) => Some( m.clone() ),
+        None => (!trimmed).then( || [1, 0, 0, 1].map( Integer::from ) ),
+    }
+}
+
+impl<S> Pull<S> {
+    pub fn source( &self ) -> &S {
+        &self.src
+    }
+    fn ends( &self, finished: bool, lo: i128 ) -> (Cow<'_, Frac>, Cow<'_, Frac>) {
+        // m(1/0) and m(lo), or just m(1/0) once the source is finished
+        let [a, b, c, d] = &self.m;
+        let inf = Frac::from_int( a.clone(), c.clone() );
+        if finished {
+            return ( Cow::Owned( inf.clone() ), Cow::Owned(inf) );
+        }
+        let l = Frac::from_int( Integer::from( a * lo ) + b, Integer::from( c * lo ) + d );
+        ( Cow::Owned(l), Cow::Owned(inf) )
+    }
+}
+
+impl<R: Real + KLogOps> TryFrom<KLog<R>> for Pull<KLog<R>> {
+    type Error = Trimmed<KLog<R>>;
+    fn try_from( src: KLog<R> ) -> Result<Self, Self::Error> {
+        let Some(m) = dropped_map( src.sink.as_ref(), src.trimmed() ) else {
+            return Err( Trimmed(src) );
+        };
+        let pos = src.len() - src.tape.len();
+        let mut p = Pull { src, m, pos };
+        p.take();
+        Ok(p)
+    }
+}
+
+impl<R: Real + KLogOps> Pull<KLog<R>> {
+    fn take( &mut self ) {
+        // x = 2 y for '1', x = (y + 1)/y for '0'
+        let first = self.src.len() - self.src.tape.len();
+        while self.pos < self.src.len() {
+            let [a, b, c, d] = std::mem::take( &mut self.m );
+            self.m = match self.src.tape[self.pos - first] {
+                1 => [ a << 1u32, b, c << 1u32, d ],
+                _ => [ Integer::from( &a + &b ), a, Integer::from( &c + &d ), c ],
+            };
+            self.pos += 1;
+        }
+    }
+    fn finished( &self ) -> bool {
+        self.src.done && self.pos == self.src.len()
+    }
+}
+
+impl<R: Real + KLogOps> Real for Pull<KLog<R>> {
+    fn enclosure( &self ) -> (Cow<'_, Frac>, Cow<'_, Frac>) {
+        self.ends( self.finished(), 1 )
+    }
+    fn refine( &mut self ) {
+        self.refine_by( 1 );
+    }
+    fn refine_by( &mut self, k: usize ) {
+        if !self.src.done {
+            let sink = self.src.sink.take();
+            self.src.red_real( k );
+            self.src.sink = sink;
+            self.take();
+            let r = if self.src.done { self.src.close_sink().map_err( |e| RedError::Io( e.kind() ) ) } else { self.src.stream() };
+            if let Err(e) = r {
+                println!("\u{274c} KLog: {}", e);
+            }
+        }
+    }
+    fn steps( &self ) -> i128 {
+        self.pos as i128
+    }
+    fn exact( &self ) -> bool {
+        self.finished()
+    }
+}
+
+impl<R: Real + RegFracOps + KLogOps> TryFrom<RegFrac<R>> for Pull<RegFrac<R>> {
+    type Error = Trimmed<RegFrac<R>>;
+    fn try_from( src: RegFrac<R> ) -> Result<Self, Self::Error> {
+        let Some(m) = dropped_map( src.sink.as_ref(), src.trimmed() ) else {
+            return Err( Trimmed(src) );
+        };
+        let pos = src.len() - src.tape.len();
+        let mut p = Pull { src, m, pos };
+        p.take();
+        Ok(p)
+    }
+}
+
+impl<R: Real + RegFracOps + KLogOps> Pull<RegFrac<R>> {
+    fn take( &mut self ) {
+        // x = t + 1/y = (t y + 1)/y
+        let first = self.src.len() - self.src.tape.len();
+        while self.pos < self.src.len() {
+            let t = Integer::from( self.src.tape[self.pos - first] );
+            let [a, b, c, d] = std::mem::take( &mut self.m );
+            self.m = [ Integer::from( &a * &t ) + b, a, Integer::from( &c * &t ) + d, c ];
+            self.pos += 1;
+        }
+    }
+    fn finished( &self ) -> bool {
+        // the remainder at 1/0 after the last term
+        self.pos == self.src.len() && self.src.remainder.exact() && !self.src.remainder.enclosure().0.finite()
+    }
+}
+
+impl<R: Real + RegFracOps + KLogOps> Real for Pull<RegFrac<R>> {
+    fn enclosure( &self ) -> (Cow<'_, Frac>, Cow<'_, Frac>) {
+        // before the first term the value is only known to be >= 0
+        self.ends( self.finished(), if self.pos == 0 { 0 } else { 1 } )
+    }
+    fn refine( &mut self ) {
+        self.refine_by( 1 );
+    }
+    fn refine_by( &mut self, k: usize ) {
+        if !self.finished() {
+            let sink = self.src.sink.take();
+            self.src.red_real( k );
+            self.src.sink = sink;
+            self.take();
+            let r = if self.finished() { self.src.close_sink().map_err( |e| RedError::Io( e.kind() ) ) } else { self.src.stream() };
+            if let Err(e) = r {
+                println!("\u{274c} RegFrac: {}", e);
+            }
+        }
+    }
+    fn steps( &self ) -> i128 {
+        self.pos as i128
+    }
+    fn exact( &self ) -> bool {
+        self.finished()
+    }
+}
@@ -0,0 +1,156 @@
+// n-th roots of Reals: x^(1/n) for x >= 0, as a Real itself, so that sqrt(pi) or cuberoot(2 e) are
+// expanded with red_real like any other node, and so is the root of a KLog or RegFrac being read
+// through real::Pull. The enclosure is that of x with its ends rooted to 'prec' bits, rounding
+// outwards with the integer root; the precision follows the width of x as it is refined.
+
+use std::borrow::Cow;
+use crate::int::{self, Integer, IntOps};
+use crate::fractions::{Frac, RegFrac, RegFracOps};
+use crate::logs::{KLog, KLogOps};
+use crate::real::{Mobius, Pull, Real, uprights};
+
+#[derive(Clone)]
+pub struct Root<R: Real> {
+    pub n: u32,
+    x: R,
+    prec: u32,          // bits after the point of the rooted ends
+    out: (Frac, Frac),  // the enclosure, kept as for Mobius
+}
+
+impl<R: Real> Root<R> {
+    pub fn new( x: R, n: u32 ) -> Option<Self> {
+        // None for n = 0, there being no 0-th root
+        (n > 0).then( || Root::build( x, n ) )
+    }
+    pub fn sqrt( x: R ) -> Self {
+        Root::build( x, 2 )
+    }
+    fn build( x: R, n: u32 ) -> Self {
+        let mut r = Root { n, x, prec: 64.min( u32::MAX / n ), out: ( Frac::from(0), Frac::from(0) ) };
+        r.out = r.bounds();
+        r
+    }
+    pub fn inner( &self ) -> &R {
+        &self.x
+    }
+
+    fn shift( n: u32, prec: u32 ) -> Option<u32> {
+        // the bits n prec the ends are shifted by, None if too many; prec is only ever raised to
+        // values where this is Some, so refining stops there
+        u64::from( n ).checked_mul( u64::from( prec ) ).and_then( |s| u32::try_from( s ).ok() )
+    }
+
+    fn end( &self, f: &Frac, up: bool ) -> Frac {
+        // f^(1/n) rounded down (or up) to a multiple of 2^-prec; 0 for f <= 0, as x >= 0 anyway
+        let (n, k) = ( self.n, self.prec );
+        let shift = Self::shift( n, k ).expect( "Root: prec beyond the shifts" );
+        let s = Integer::from( &f.num << shift );
+        let t = if up { s.div_rem_ceil_of( &f.den ).0 } else { s.div_rem_floor_of( &f.den ).0 };
+        if t <= 0 {
+            return Frac::from(0);
+        }
+        let mut r = t.root_of( n );
+        if up && r.pow_of( n ) < t {
+            r += 1;
+        }
+        let mut z = Frac::from_int( r, Integer::from(1) << k );
+        z.pow2_div();
+        z
+    }
+
+    fn exact_root( &self, x: &Frac ) -> Option<Frac> {
+        // the root of a rational when it is one, i.e. of both terms in lowest terms
+        let mut x = x.clone();
+        x.normalise();
+        let (p, q) = ( x.num.root_of( self.n ), x.den.root_of( self.n ) );
+        if p.pow_of( self.n ) == x.num && q.pow_of( self.n ) == x.den {
+            Some( Frac::from_int( p, q ) )
+        } else {
+            None
+        }
+    }
+
+    fn bounds( &self ) -> (Frac, Frac) {
+        // After sorting an infinite lower end is -1/0, leaving 0 as the lower bound of the root, and
+        // an infinite upper end stays one; both is unbounded. An enclosure wholly below 0, which a
+        // non-bracketing x can pass through, gives no root either, so it is unbounded as well and
+        // the reductions wait for x to be refined (or for their budget to run out).
+        let (l, u) = uprights( &self.x );
+        let (lo, hi) = if l <= u { (l, u) } else { (u, l) };
+        match (lo.finite(), hi.finite()) {
+            (false, false) => ( Frac::from_i128(1, 0), Frac::from_i128(-1, 0) ),
+            (false, true) => ( Frac::from(0), self.end( &hi, true ) ),
+            (true, false) => ( self.end( &lo, false ), Frac::from_i128(1, 0) ),
+            (true, true) if hi < Frac::from(0) => ( Frac::from_i128(1, 0), Frac::from_i128(-1, 0) ),
+            (true, true) => {
+                if self.x.exact() {
+                    if let Some(z) = self.exact_root( &lo ) {
+                        return ( z.clone(), z );
+                    }
+                }
+                ( self.end( &lo, false ), self.end( &hi, true ) )
+            },
+        }
+    }
+}
+
+impl<R: Real> Real for Root<R> {
+    fn enclosure( &self ) -> (Cow<'_, Frac>, Cow<'_, Frac>) {
+        ( Cow::Borrowed( &self.out.0 ), Cow::Borrowed( &self.out.1 ) )
+    }
+    fn refine( &mut self ) {
+        self.refine_by( 1 );
+    }
+    fn refine_by( &mut self, k: usize ) {
+        // An exact x only needs more bits, otherwise as many as x is now known to, and some more
+        // for the slope of the root above 1 (below 1 the rounding is the smaller part anyway)
+        if self.x.exact() {
+            let p = self.prec.saturating_mul(2);
+            if Self::shift( self.n, p ).is_some() {
+                self.prec = p;
+            }
+        } else {
+            self.x.refine_by( k );
+            let (l, u) = uprights( &self.x );
+            if l.finite() && u.finite() {
+                let w = Integer::from( &u.num * &l.den ) - &l.num * &u.den;
+                let d = Integer::from( &u.den * &l.den );
+                let scale = int::log2( &u.num ).max( int::log2( &l.num ) ) - int::log2( &u.den ).min( int::log2( &l.den ) );
+                let bits = int::log2( &d ) - int::log2( &w ) + 16.0 + scale.max(0.0);
+                if bits.is_finite() && bits > self.prec as f64 {
+                    let p = bits.ceil() as u32;
+                    if Self::shift( self.n, p ).is_some() {
+                        self.prec = p;
+                    }
+                }
+            }
+        }
+        self.out = self.bounds();
+    }
+    fn steps( &self ) -> i128 {
+        self.x.steps()
+    }
+    fn exact( &self ) -> bool {
+        self.x.exact() && self.out.0 == self.out.1
+    }
+}
+
+// the expansions of the roots of expansions
+pub type KLogRoot<R> = KLog<Mobius<Root<Pull<KLog<R>>>>>;
+pub type RegFracRoot<R> = RegFrac<Mobius<Root<Pull<RegFrac<R>>>>>;
+
+impl<R: Real + KLogOps> KLog<R> {
+    pub fn root( self, n: u32 ) -> Option<KLogRoot<R>> {
+        // the k-log of x^(1/n), pulling the digits of this one as they are needed; None for n = 0,
+        // or if the sink trimmed the tape without keeping its map
+        Some( KLog::from( Mobius::from( Root::new( Pull::try_from( self ).ok()?, n )? ) ) )
+    }
+}
+
+impl<R: Real + RegFracOps + KLogOps> RegFrac<R> {
+    pub fn root( self, n: u32 ) -> Option<RegFracRoot<R>> {
+        // the continued fraction of x^(1/n), pulling the terms of this one as they are needed; None
+        // for n = 0, or if the sink trimmed the tape without keeping its map
+        Some( RegFrac::from( Mobius::from( Root::new( Pull::try_from( self ).ok()?, n )? ) ) )
+    }
+}
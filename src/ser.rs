@@ -0,0 +1,199 @@
+// Serde support (feature "serde"), for storing and exchanging results. Big integers are written as
+// signed hex strings in human readable formats (JSON, ...) and as sign + magnitude bytes in binary ones
+// (CBOR, ...); k-log tapes are packed 8 digits to the byte. What can't be serialised -- generators,
+// observers, normalisation policies -- is left out and comes back as the defaults, so KFrac goes by
+// the math_consts name of its generator and a Series only gives its state.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde::de::{self, Visitor};
+use serde::ser::SerializeTuple;
+use crate::int::{Integer, IntOps};
+use crate::fractions::{Frac, KFrac};
+use crate::series::Series;
+
+fn to_hex( b: &[u8] ) -> String {
+    b.iter().map( |x| format!( "{:02x}", x ) ).collect()
+}
+
+fn from_hex( s: &str ) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) || !s.bytes().all( |c| c.is_ascii_hexdigit() ) {
+        return None;
+    }
+    (0..s.len()).step_by(2).map( |i| u8::from_str_radix( &s[i..i+2], 16 ).ok() ).collect()
+}
+
+struct Bytes;
+
+impl Visitor<'_> for Bytes {
+    type Value = Vec<u8>;
+    fn expecting( &self, f: &mut std::fmt::Formatter ) -> std::fmt::Result {
+        write!( f, "bytes" )
+    }
+    fn visit_bytes<E: de::Error>( self, v: &[u8] ) -> Result<Vec<u8>, E> {
+        Ok( v.to_vec() )
+    }
+    fn visit_byte_buf<E: de::Error>( self, v: Vec<u8> ) -> Result<Vec<u8>, E> {
+        Ok( v )
+    }
+}
+
+// The newtypes below carry the encodings, as Integer and the tapes are not our types (or plain Vecs)
+
+struct Packed<'a>( &'a [u8] );
+
+impl Serialize for Packed<'_> {
+    fn serialize<S: Serializer>( &self, s: S ) -> Result<S::Ok, S::Error> {
+        if s.is_human_readable() {
+            s.serialize_str( &to_hex( self.0 ) )
+        } else {
+            s.serialize_bytes( self.0 )
+        }
+    }
+}
+
+struct Unpacked( Vec<u8> );
+
+impl<'de> Deserialize<'de> for Unpacked {
+    fn deserialize<D: Deserializer<'de>>( d: D ) -> Result<Self, D::Error> {
+        if d.is_human_readable() {
+            let s = String::deserialize(d)?;
+            from_hex( &s ).map( Unpacked ).ok_or_else( || de::Error::custom( "bad hex bytes" ) )
+        } else {
+            d.deserialize_bytes( Bytes ).map( Unpacked )
+        }
+    }
+}
+
+struct Int<'a>( &'a Integer );
+
+impl Serialize for Int<'_> {
+    fn serialize<S: Serializer>( &self, s: S ) -> Result<S::Ok, S::Error> {
+        if s.is_human_readable() {
+            s.serialize_str( &self.0.to_string_radix(16) )
+        } else {
+            let mut b = vec![ u8::from( *self.0 < 0 ) ];
+            b.extend( self.0.magnitude_be() );
+            Packed( &b ).serialize(s)
+        }
+    }
+}
+
+struct IntDe( Integer );
+
+impl<'de> Deserialize<'de> for IntDe {
+    fn deserialize<D: Deserializer<'de>>( d: D ) -> Result<Self, D::Error> {
+        if d.is_human_readable() {
+            let s = String::deserialize(d)?;
+            Integer::from_str_radix( &s, 16 ).map( IntDe ).map_err( |_| de::Error::custom( format!( "bad integer '{}'", s ) ) )
+        } else {
+            let Unpacked(b) = Unpacked::deserialize(d)?;
+            match b.split_first() {
+                Some((&0, m)) => Ok( IntDe( Integer::from_magnitude_be(m) ) ),
+                Some((&1, m)) => Ok( IntDe( -Integer::from_magnitude_be(m) ) ),
+                _ => Err( de::Error::custom( "bad integer bytes" ) ),
+            }
+        }
+    }
+}
+
+impl Serialize for Frac {
+    // as the pair (num, den), unreduced
+    fn serialize<S: Serializer>( &self, s: S ) -> Result<S::Ok, S::Error> {
+        let mut t = s.serialize_tuple(2)?;
+        t.serialize_element( &Int( &self.num ) )?;
+        t.serialize_element( &Int( &self.den ) )?;
+        t.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Frac {
+    fn deserialize<D: Deserializer<'de>>( d: D ) -> Result<Self, D::Error> {
+        let (IntDe(num), IntDe(den)) = <(IntDe, IntDe)>::deserialize(d)?;
+        Ok( Frac::from_int( num, den ) )
+    }
+}
+
+pub(crate) mod bits {
+    // A tape of 0/1 digits as (length, packed bytes), most significant bit first
+    use super::*;
+
+    pub fn serialize<S: Serializer>( tape: &[u8], s: S ) -> Result<S::Ok, S::Error> {
+        let packed: Vec<u8> = tape.chunks(8).map( |c| {
+            c.iter().enumerate().fold( 0u8, |b, (i, &q)| b | ((q & 1) << (7 - i)) )
+        }).collect();
+        (tape.len() as u64, Packed( &packed )).serialize(s)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>( d: D ) -> Result<Vec<u8>, D::Error> {
+        let (len, Unpacked(packed)) = <(u64, Unpacked)>::deserialize(d)?;
+        let len = len as usize;
+        if packed.len() != len.div_ceil(8) {
+            return Err( de::Error::custom( "tape length doesn't match its bytes" ) );
+        }
+        Ok( (0..len).map( |i| (packed[i / 8] >> (7 - i % 8)) & 1 ).collect() )
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct KFracDesc {
+    // A KFrac of a math_consts generator, at convergent n (tail and observer are not kept)
+    pub name: String,
+    pub n: i128,
+    pub frac1: Frac,
+    pub frac2: Frac,
+}
+
+impl KFracDesc {
+    pub fn kfrac( &self ) -> Option<KFrac> {
+        // None for names not in math_consts
+        let mut k = KFrac::by_name( &self.name )?;
+        k.n = self.n;
+        k.frac1 = self.frac1.clone();
+        k.frac2 = self.frac2.clone();
+        Some(k)
+    }
+}
+
+impl KFrac {
+    pub fn descriptor( &self ) -> Option<KFracDesc> {
+        // None without a name, i.e. for KFracs not made by KFrac::by_name
+        Some( KFracDesc { name: self.name?.to_string(), n: self.n, frac1: self.frac1.clone(), frac2: self.frac2.clone() } )
+    }
+}
+
+impl Serialize for KFrac {
+    fn serialize<S: Serializer>( &self, s: S ) -> Result<S::Ok, S::Error> {
+        match self.descriptor() {
+            Some(d) => d.serialize(s),
+            None => Err( serde::ser::Error::custom( "only KFracs of math_consts (KFrac::by_name) can be serialised" ) ),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for KFrac {
+    fn deserialize<D: Deserializer<'de>>( d: D ) -> Result<Self, D::Error> {
+        let desc = KFracDesc::deserialize(d)?;
+        desc.kfrac().ok_or_else( || de::Error::custom( format!( "unknown constant '{}'", desc.name ) ) )
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SeriesState {
+    // Where a Series is: the terms themselves are functions, given again when resuming
+    pub n: i128,
+    pub curr: Frac,
+    pub sum: Frac,
+}
+
+impl Series {
+    pub fn state( &self ) -> SeriesState {
+        SeriesState { n: self.n, curr: self.curr.clone(), sum: self.sum.clone() }
+    }
+    pub fn with_state( mut self, s: SeriesState ) -> Self {
+        // resume a Series (made with the same terms) where the state was taken
+        self.n = s.n;
+        self.curr = s.curr;
+        self.sum = s.sum;
+        self
+    }
+}
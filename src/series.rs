@@ -1,14 +1,43 @@
-// Series with rational terms
+// Series with rational terms, and their continued fractions
 
-use crate::fractions::Frac;
+use crate::int::Integer;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use crate::fractions::{Frac, Gen};
 
 type PropS = fn(i128, &Frac) -> Frac;
 type PropSm = fn(i128, &mut Frac);
+type PropSd = dyn Fn(i128, &mut Frac) + Send + Sync;
+
+#[derive(Clone)]
+enum Terms {
+    // The next term from the previous one, as for Gen
+    Fn(PropSm),
+    Dyn(Arc<PropSd>),
+}
+
+impl Terms {
+    fn next( &self, n: i128, x: &mut Frac ) {
+        match self {
+            Terms::Fn(u) => u(n, x),
+            Terms::Dyn(u) => u(n, x),
+        }
+    }
+}
+
+impl std::fmt::Debug for Terms {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Terms::Fn(u) => write!( f, "{:?}", u ),
+            Terms::Dyn(_) => write!( f, "<closure>" ),
+        }
+    }
+}
 
 #[derive(Debug)]
 pub struct Series {
     pub n: i128,
-    u: PropSm,
+    u: Terms,
     pub curr: Frac,
     pub err: PropS,
     pub sum: Frac,
@@ -16,12 +45,19 @@ pub struct Series {
 
 impl Series {
     pub fn from( u: PropSm, err: PropS ) -> Self {
+        Series::with_terms( Terms::Fn(u), err )
+    }
+    pub fn new( u: impl Fn(i128, &mut Frac) + Send + Sync + 'static, err: PropS ) -> Self {
+        // closures for the terms, e.g. from the bindings
+        Series::with_terms( Terms::Dyn( Arc::new(u) ), err )
+    }
+    fn with_terms( u: Terms, err: PropS ) -> Self {
         let mut undef = Frac::from(0);
-        u(0, &mut undef); // u_0 should not depend on the value of undef (u_{-1})
+        u.next(0, &mut undef); // u_0 should not depend on the value of undef (u_{-1})
         Series { 
             n: 0, 
-            u: u, 
-            err: err, 
+            u, 
+            err, 
             sum: undef.clone(),
             curr: undef }
     }
@@ -32,9 +68,91 @@ impl Series {
 
     pub fn prop( &mut self ) {
         self.n += 1;
-        (self.u)(self.n, &mut self.curr);
+        self.u.next(self.n, &mut self.curr);
         self.sum.num *= &self.curr.den;
         self.sum.num += &self.curr.num * &self.sum.den;
         self.sum.den *= &self.curr.den;
     }
 }
+
+impl Series {
+    pub fn euler( &self ) -> Gen {
+        // Euler's continued fraction t_0/(1 - r_1/(1 + r_1 - r_2/(1 + r_2 - ...))), r_n = t_n/t_{n-1},
+        // cleared of denominators, whose n-th convergent is the sum of the first n terms.
+        // The terms are generated afresh, and must not vanish.
+        let u = self.u.clone();
+        let mut t0 = Frac::from(0);
+        u.next(0, &mut t0);
+        t0.normalise();
+        // the current term and [t_0, r_1, r_2, ...]
+        let state = Mutex::new( (t0.clone(), vec![t0]) );
+        Gen::new( move |n| {
+            if n == 0 {
+                return (Integer::from(1), Integer::from(0));
+            }
+            // a term that unwound (a failed Python callback) left the state as before, so a lock
+            // it poisoned is taken over
+            let mut st = state.lock().unwrap_or_else( PoisonError::into_inner );
+            let (curr, r) = &mut *st;
+            while r.len() < n as usize {
+                let prev = curr.clone();
+                u.next(r.len() as i128, curr);
+                curr.normalise();
+                r.push( &*curr / &prev );
+            }
+            let k = n as usize - 1;
+            if k == 0 {
+                return (r[0].num.clone(), r[0].den.clone());
+            }
+            // a_n = -q_{n-2} p_{n-1}, b_n = q_{n-1} + p_{n-1} with r_k = p_k/q_k (and t_0 for k = 0)
+            ( -Integer::from(&r[k-1].den * &r[k].num), Integer::from(&r[k].den + &r[k].num) )
+        })
+    }
+
+    pub fn from_power_series( c: &[Frac], z: &Frac ) -> Option<Gen> {
+        // The value at z of the power series c_0 + c_1 z + c_2 z^2 + ... through its corresponding
+        // fraction c_0/(1 - al_1 z/(1 - al_2 z/(1 - ...))), with al_k from qd. The fraction ends
+        // (a_n = 0) after the len(c)-1 known coefficients; None if the qd algorithm breaks down.
+        let al = qd(c)?;
+        let c0 = c.first()?;
+        let mut terms = vec![ (c0.num.clone(), c0.den.clone()) ];
+        let mut q0 = c0.den.clone();
+        for a in al.iter() {
+            let s = a * z;
+            terms.push( (-(q0 * &s.num), s.den.clone()) );
+            q0 = s.den;
+        }
+        Some( Gen::new( move |n| {
+            match n {
+                0 => (Integer::from(1), Integer::from(0)),
+                _ => terms.get(n as usize - 1).cloned().unwrap_or( (Integer::from(0), Integer::from(1)) ),
+            }
+        }))
+    }
+}
+
+pub fn qd( c: &[Frac] ) -> Option<Vec<Frac>> {
+    // Quotient-difference algorithm: the coefficients al_1, al_2, ... of the corresponding fraction
+    // c_0/(1 - al_1 z/(1 - al_2 z/(1 - ...))) of a power series, one from each coefficient after c_0.
+    // None when a division by zero breaks the scheme down.
+    if c.iter().any( |ck| ck.num == 0 ) {
+        return None;
+    }
+    let mut al = Vec::new();
+    let mut q: Vec<Frac> = c.windows(2).map( |w| &w[1] / &w[0] ).collect();
+    let mut e: Vec<Frac> = vec![ Frac::from(0); q.len() + 1 ];
+    while let Some(q0) = q.first() {
+        al.push( q0.clone() );
+        // rhombus rules: e_m^k = q_m^{k+1} - q_m^k + e_{m-1}^{k+1}, q_{m+1}^k = q_m^{k+1} e_m^{k+1} / e_m^k
+        e = (0..q.len()-1).map( |k| &(&q[k+1] - &q[k]) + &e[k+1] ).collect();
+        let Some(e0) = e.first() else {
+            break;
+        };
+        al.push( e0.clone() );
+        if e.iter().any( |ek| ek.num == 0 ) {
+            return None;
+        }
+        q = (0..e.len()-1).map( |k| &(&q[k+1] * &e[k+1]) / &e[k] ).collect();
+    }
+    Some(al)
+}
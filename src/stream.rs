@@ -0,0 +1,237 @@
+// Streaming of tapes: a Sink attached to a KLog or RegFrac is handed the digits (or terms) as the
+// reductions produce them, so very long expansions can go to a file or any other io::Write instead
+// of piling up in memory. With a tail window the tape only keeps the last so many digits, while
+// len() still counts them all, and with_map keeps the Mobius map of the dropped part, so the interval
+// of the whole expansion stays at hand.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use crate::int::Integer;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Bits,   // k-log digits packed 8 to the byte, most significant bit first (the last byte zero padded)
+    Digits, // k-log digits as ASCII '0'/'1', without separators
+    Runs,   // the shorthand of gen_tape, one run per line
+    Terms,  // continued fraction terms, one per line
+}
+// In the text formats (Runs, Terms) an unfinished expansion ends with a "..." line, as in gen_str:
+// for k-log digits after the open run, which is written even without any '1's.
+
+pub struct Sink {
+    out: BufWriter<Box<dyn Write + Send>>,
+    pub format: Format,
+    pub flush: usize,        // flush the writer every so many digits (or terms), 0 for only at close
+    pub tail: Option<usize>, // digits (or terms) kept in the tape, None for all of them
+    written: usize,          // from the start of the expansion
+    dropped: usize,          // from the front of the tape
+    byte: (u8, u32),         // Bits: the byte being filled and its number of bits
+    run: u128,               // Runs: the '1's of the open run
+    digits: bool,            // whether k-log digits were written, rather than terms
+    map: Option<[Integer; 4]>, // with_map: [a, b, c, d], x = (a r + b)/(c r + d) for the dropped part
+    closed: bool,
+}
+
+impl Sink {
+    pub fn new( w: impl Write + Send + 'static, format: Format ) -> Self {
+        Sink {
+            out: BufWriter::new( Box::new(w) ),
+            format,
+            flush: 1 << 16,
+            tail: None,
+            written: 0,
+            dropped: 0,
+            byte: (0, 0),
+            run: 0,
+            digits: false,
+            map: None,
+            closed: false,
+        }
+    }
+    pub fn create( path: impl AsRef<Path>, format: Format ) -> io::Result<Self> {
+        Ok( Sink::new( File::create(path)?, format ) )
+    }
+    pub fn with_flush( mut self, n: usize ) -> Self {
+        self.flush = n;
+        self
+    }
+    pub fn with_tail( mut self, n: usize ) -> Self {
+        self.tail = Some(n);
+        self
+    }
+    pub fn with_map( mut self ) -> Self {
+        // Folds the digits (or terms) dropped from the tape into a Mobius map, which costs about
+        // one product of numbers the size of the map per trim
+        self.map = Some( [1, 0, 0, 1].map( Integer::from ) );
+        self
+    }
+    pub fn map( &self ) -> Option<&[Integer; 4]> {
+        self.map.as_ref()
+    }
+    pub fn written( &self ) -> usize {
+        self.written
+    }
+    pub fn dropped( &self ) -> usize {
+        self.dropped
+    }
+
+    pub(crate) fn digits( &mut self, tape: &mut Vec<u8> ) -> io::Result<()> {
+        // write the digits of the tape not written yet, then trim it to the tail window
+        self.digits = true;
+        let new = Self::unwritten( tape, self.written - self.dropped )?;
+        if new.is_empty() {
+            return Ok(());
+        }
+        if self.closed {
+            return Err( io::Error::other( "the sink is closed" ) );
+        }
+        let mut buf = Vec::with_capacity( new.len() );
+        match self.format {
+            Format::Bits => {
+                for &d in new {
+                    self.byte = ((self.byte.0 << 1) | (d & 1), self.byte.1 + 1);
+                    if self.byte.1 == 8 {
+                        buf.push( self.byte.0 );
+                        self.byte = (0, 0);
+                    }
+                }
+            },
+            Format::Digits => buf.extend( new.iter().map( |d| b'0' + d ) ),
+            Format::Runs | Format::Terms => {
+                // the runs are the terms of a k-log
+                for &d in new {
+                    if d == 1 {
+                        self.run += 1;
+                    } else {
+                        writeln!( buf, "{}", self.run )?;
+                        self.run = 0;
+                    }
+                }
+            },
+        }
+        self.emit( &buf, new.len() )?;
+        self.trim( tape, digit_map );
+        Ok(())
+    }
+
+    pub(crate) fn terms( &mut self, tape: &mut Vec<u128> ) -> io::Result<()> {
+        // As digits, for continued fraction terms: 16 bytes big-endian each as Bits, otherwise
+        // one per line
+        let new = Self::unwritten( tape, self.written - self.dropped )?;
+        if new.is_empty() {
+            return Ok(());
+        }
+        if self.closed {
+            return Err( io::Error::other( "the sink is closed" ) );
+        }
+        let mut buf = Vec::new();
+        for &a in new {
+            match self.format {
+                Format::Bits => buf.extend( a.to_be_bytes() ),
+                _ => writeln!( buf, "{}", a )?,
+            }
+        }
+        self.emit( &buf, new.len() )?;
+        self.trim( tape, term_map );
+        Ok(())
+    }
+
+    fn unwritten<D>( tape: &[D], from: usize ) -> io::Result<&[D]> {
+        // the tape past what was written, which fails if it was cut short other than by trim
+        tape.get( from.. ).ok_or_else( || io::Error::new( io::ErrorKind::InvalidInput,
+            "the tape is shorter than what the sink has written of it" ) )
+    }
+
+    fn emit( &mut self, buf: &[u8], n: usize ) -> io::Result<()> {
+        self.out.write_all( buf )?;
+        let before = self.written;
+        self.written += n;
+        if self.flush > 0 && self.written / self.flush > before / self.flush {
+            self.out.flush()?;
+        }
+        Ok(())
+    }
+
+    fn trim<D>( &mut self, tape: &mut Vec<D>, mat: fn( &D ) -> [Integer; 4] ) {
+        // only once the tape is twice the window, so the shifting costs O(1) per digit
+        if let Some(t) = self.tail {
+            if tape.len() >= 2 * t.max(1) {
+                let k = tape.len() - t;
+                if let Some(m) = self.map.as_mut() {
+                    *m = compose( m, &mobius( &tape[..k], mat ) );
+                }
+                tape.drain( ..k );
+                self.dropped += k;
+            }
+        }
+    }
+
+    pub fn close( &mut self ) -> io::Result<()> {
+        // Writes out the last partial byte (Bits), or the open run and the unfinished marker (Runs),
+        // and flushes. Nothing can be written after, as it would not line up; dropping the Sink
+        // closes it too, but silently.
+        self.end( false )
+    }
+
+    pub fn finish( &mut self ) -> io::Result<()> {
+        // As close, for an expansion that has ended: no marker, and no open run after the last '0'
+        self.end( true )
+    }
+
+    fn end( &mut self, done: bool ) -> io::Result<()> {
+        if !self.closed {
+            self.closed = true;
+            match self.format {
+                Format::Bits if self.byte.1 > 0 => {
+                    let b = self.byte.0 << (8 - self.byte.1);
+                    self.out.write_all( &[b] )?;
+                },
+                Format::Runs | Format::Terms if !done => {
+                    if self.digits {
+                        writeln!( self.out, "{}", self.run )?;
+                    }
+                    writeln!( self.out, "..." )?;
+                },
+                _ => {},
+            }
+        }
+        self.out.flush()
+    }
+}
+
+pub(crate) fn digit_map( d: &u8 ) -> [Integer; 4] {
+    // x = 2r for '1', x = (r + 1)/r for '0'
+    if *d == 1 { [2, 0, 0, 1] } else { [1, 1, 1, 0] }.map( Integer::from )
+}
+
+pub(crate) fn term_map( a: &u128 ) -> [Integer; 4] {
+    // x = a + 1/r
+    [Integer::from(*a), Integer::from(1), Integer::from(1), Integer::from(0)]
+}
+
+pub(crate) fn mobius<D>( tape: &[D], mat: fn( &D ) -> [Integer; 4] ) -> [Integer; 4] {
+    // The map of a whole tape, multiplied out as a balanced tree so the sizes stay even
+    match tape.len() {
+        0 => [1, 0, 0, 1].map( Integer::from ),
+        1 => mat( &tape[0] ),
+        n => compose( &mobius( &tape[..n/2], mat ), &mobius( &tape[n/2..], mat ) ),
+    }
+}
+
+pub(crate) fn compose( m: &[Integer; 4], n: &[Integer; 4] ) -> [Integer; 4] {
+    let [a, b, c, d] = m;
+    let [e, f, g, h] = n;
+    [
+        Integer::from( a * e ) + Integer::from( b * g ),
+        Integer::from( a * f ) + Integer::from( b * h ),
+        Integer::from( c * e ) + Integer::from( d * g ),
+        Integer::from( c * f ) + Integer::from( d * h ),
+    ]
+}
+
+impl Drop for Sink {
+    fn drop( &mut self ) {
+        let _ = self.close();
+    }
+}
@@ -0,0 +1,127 @@
+// Transformations of generalized continued fractions b_0 + K(a_n/b_n), acting on their generators.
+// Contractions and Bauer-Muir transforms naturally have rational terms, so they are given here in
+// equivalent forms with integer terms, which leaves all the convergents unchanged.
+
+use crate::int::Integer;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use crate::fractions::{Frac, Gen, KFrac, RegFrac};
+
+impl Gen {
+    pub fn even( &self ) -> Gen {
+        // Even contraction, its convergents are f_0, f_2, f_4, ... (requires b_2n != 0)
+        let g = self.clone();
+        Gen::new( move |m| {
+            if m == 0 {
+                return g.ab(0);
+            }
+            let (a1, b1) = g.ab(2*m - 1);
+            let (a2, b2) = g.ab(2*m);
+            if m == 1 {
+                return ( a1 * &b2, b1 * &b2 + a2 );
+            }
+            let (a0, b0) = g.ab(2*m - 2);
+            let r = if m >= 3 { g.ab(2*m - 4).1 } else { Integer::from(1) };
+            let b = b0 * (b1 * &b2 + a2) + Integer::from(&a1 * &b2);
+            ( -(r * a0 * a1 * b2), b )
+        })
+    }
+
+    pub fn odd( &self ) -> Gen {
+        // Odd contraction, its convergents are f_0, f_1, f_3, f_5, ... (requires b_2n+1 != 0),
+        // where the leading f_0 keeps b_0 an integer
+        let g = self.clone();
+        Gen::new( move |m| {
+            if m <= 1 {
+                return g.ab(m);
+            }
+            let (a2, b2) = g.ab(2*m - 2);
+            let (a3, b3) = g.ab(2*m - 1);
+            if m == 2 {
+                return ( a2 * &b3, b2 * &b3 + a3 );
+            }
+            let (a1, b1) = g.ab(2*m - 3);
+            let r = if m >= 4 { g.ab(2*m - 5).1 } else { Integer::from(1) };
+            let b = b1 * (b2 * &b3 + a3) + Integer::from(&a2 * &b3);
+            ( -(r * a1 * a2 * b3), b )
+        })
+    }
+
+    pub fn equivalence( &self, c: impl Fn(i128) -> Integer + Send + Sync + 'static ) -> Gen {
+        // a_n -> c_{n-1} c_n a_n, b_n -> c_n b_n with c_0 = 1, all c_n != 0
+        let g = self.clone();
+        Gen::new( move |n| {
+            let (a, b) = g.ab(n);
+            if n == 0 {
+                return (a, b);
+            }
+            let cn = c(n);
+            let cp = if n > 1 { c(n - 1) } else { Integer::from(1) };
+            ( a * cp * &cn, b * cn )
+        })
+    }
+
+    pub fn unit_numerators( &self, count: usize ) -> Vec<Frac> {
+        // b_0, b_1/a_1, b_2 a_1/a_2, ... the partial denominators of the equivalent fraction with all
+        // a_n = 1. These are rational, hence a list rather than a generator.
+        let mut c = Frac::from(1);
+        let mut terms = vec![ Frac::from( self.ab(0).1 ) ];
+        for n in 1..=(count as i128) {
+            let (a, b) = self.ab(n);
+            c = Frac::from_int( c.den, a * c.num );
+            c.normalise();
+            let mut t = Frac::from_int( b * &c.num, c.den.clone() );
+            t.normalise();
+            terms.push( t );
+        }
+        terms
+    }
+
+    pub fn bauer_muir( &self, w: impl Fn(i128) -> Integer + Send + Sync + 'static ) -> Gen {
+        // Bauer-Muir transform with respect to the modifiers w_n, i.e. the fraction whose convergents are
+        // the modified approximants (A_n + w_n A_{n-1})/(B_n + w_n B_{n-1}). A good choice of w_n, close
+        // to the tails, accelerates convergence. Requires l_n = a_n - w_{n-1}(b_n + w_n) != 0.
+        let g = self.clone();
+        let w = Arc::new(w);
+        let lambda = {
+            let (g, w) = (g.clone(), w.clone());
+            move |n: i128| {
+                let (a, b) = g.ab(n);
+                a - w(n - 1) * (b + w(n))
+            }
+        };
+        Gen::new( move |n| {
+            let (a, b) = g.ab(n);
+            match n {
+                0 => ( a, b + w(0) ),
+                1 => ( lambda(1), b + w(1) ),
+                _ => {
+                    let l = lambda(n);
+                    let r = if n >= 3 { lambda(n - 2) } else { Integer::from(1) };
+                    let d = lambda(n - 1) * (b + w(n)) - w(n - 2) * &l;
+                    ( r * g.ab(n - 1).0 * l, d )
+                }
+            }
+        })
+    }
+
+    pub fn regular( &self ) -> Gen {
+        // The equivalent regular fraction (a_n = 1), expanded on demand by RegFrac, for values >= 0.
+        // A rational value ends with (0, 1) terms after its last one, as from_power_series does.
+        let r = Mutex::new( RegFrac::from( KFrac::from_gen( self.clone() ) ) );
+        Gen::new( move |n| {
+            // as in Series::euler, a generator that unwound poisons the lock but leaves r as it was
+            let mut r = r.lock().unwrap_or_else( PoisonError::into_inner );
+            while r.tape.len() <= n as usize {
+                // both convergents at 1/0 make the determinant 0, so some a_k was: the fraction has
+                // ended and all its terms are out
+                let rem = &r.remainder;
+                if !rem.frac1.finite() && !rem.frac2.finite() {
+                    return ( Integer::from(0), Integer::from(1) );
+                }
+                r.red1(1);
+            }
+            ( Integer::from(1), Integer::from( r.tape[n as usize] ) )
+        })
+    }
+}
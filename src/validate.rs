@@ -0,0 +1,101 @@
+// Checks of a generator before trusting it to the reductions: KLog<KFrac> and RegFrac<KFrac> assume
+// that the fraction converges and that every two successive convergents bracket the value.
+// The criteria are only checked on the terms of a finite prefix, so they can't prove anything about
+// the rest, but they catch most bad or mistyped *_ab functions.
+
+use std::fmt::Display;
+
+use crate::fractions::{Frac, Gen, KFrac};
+use crate::int;
+
+#[derive(Clone, Debug)]
+pub struct Validation {
+    pub terms: i128,           // length of the checked prefix
+    pub pringsheim: bool,      // |b_n| >= |a_n| + 1 (Sleszynski-Pringsheim): converges, |value - b_0| <= 1
+    pub worpitzky: bool,       // 4|a_n| <= |b_{n-1} b_n| for n >= 2: converges
+    pub positive: bool,        // a_n, b_n > 0: the convergents alternate around the value
+    pub bracketing: Option<i128>, // first n with the last convergent not between f_{n-1} and f_n
+    pub shrinking: Option<i128>,  // first n with |f_n - f_{n-1}| larger than the previous difference
+    pub rate: f64,             // observed bits per term over the second half (inf if it terminates)
+}
+
+impl Validation {
+    pub fn ok( &self ) -> bool {
+        // good enough for the reductions, as far as the prefix can tell
+        self.bracketing.is_none() && self.shrinking.is_none() && self.rate > 0.0
+    }
+}
+
+impl Display for Validation {
+    fn fmt( &self, f: &mut std::fmt::Formatter ) -> std::fmt::Result {
+        let mark = |b: bool| if b { "\u{2714}" } else { "\u{2717}" };
+        writeln!( f, "Validation of {} terms:", self.terms )?;
+        writeln!( f, "  Sleszynski-Pringsheim {}, Worpitzky {}, positive terms {}",
+            mark(self.pringsheim), mark(self.worpitzky), mark(self.positive) )?;
+        match self.bracketing {
+            None => writeln!( f, "  bracketing \u{2714}" )?,
+            Some(n) => writeln!( f, "  bracketing \u{2717} (from convergent {})", n )?,
+        }
+        match self.shrinking {
+            None => writeln!( f, "  shrinking \u{2714}" )?,
+            Some(n) => writeln!( f, "  shrinking \u{2717} (from convergent {})", n )?,
+        }
+        write!( f, "  rate {:.3} bits per term", self.rate )
+    }
+}
+
+impl Gen {
+    pub fn validate( &self, terms: usize ) -> Validation {
+        // Checks the first 'terms' terms, with the last convergent standing in for the value
+        let terms = terms.max(4) as i128;
+        let (mut pringsheim, mut worpitzky, mut positive) = (true, true, true);
+        let mut b_prev = self.ab(0).1;
+        for n in 1..=terms {
+            let (a, b) = self.ab(n);
+            pringsheim &= b.clone().abs() > a.clone().abs();
+            if n >= 2 {
+                worpitzky &= a.clone().abs() * 4 <= (b.clone() * &b_prev).abs();
+            }
+            positive &= a > 0 && b > 0;
+            b_prev = b;
+        }
+
+        let mut k = KFrac::from_gen( self.clone() );
+        let mut conv = vec![ k.frac2.clone() ];
+        for _ in 0..terms {
+            k.prop();
+            let mut f = k.frac2.clone();
+            f.normalise();
+            conv.push( f );
+        }
+        let last = &conv[terms as usize];
+
+        // only the first half is compared against the last convergent, which should be far better
+        let bracketing = (1..=terms/2).find( |&n| {
+            let (f0, f1) = ( &conv[n as usize - 1], &conv[n as usize] );
+            let (lo, hi) = if f0 <= f1 { (f0, f1) } else { (f1, f0) };
+            last < lo || last > hi
+        });
+
+        let mut widths: Vec<Frac> = vec![];
+        let mut shrinking = None;
+        for n in 1..=terms {
+            let w = (&conv[n as usize] - &conv[n as usize - 1]).abs();
+            if shrinking.is_none() && widths.last().is_some_and( |p| &w > p ) {
+                shrinking = Some(n);
+            }
+            widths.push( w );
+        }
+
+        let log2 = |w: &Frac| -> Option<f64> {
+            if w.num == 0 { None } else { Some( int::log2( &w.num ) - int::log2( &w.den ) ) }
+        };
+        let h = terms / 2;
+        let rate = match ( log2( &widths[h as usize - 1] ), log2( &widths[terms as usize - 1] ) ) {
+            (Some(w0), Some(w1)) => (w0 - w1) / (terms - h) as f64,
+            (_, None) => f64::INFINITY,
+            (None, Some(_)) => 0.0,
+        };
+        Validation { terms, pringsheim, worpitzky, positive, bracketing, shrinking, rate }
+    }
+}